@@ -0,0 +1,693 @@
+//! Typed structs for A3DA (Auth 3D) text files, which animate the cameras, lights and objects
+//! of a PV.
+//!
+//! Every animated property is a [`KeySet`], and [`from_str`] and [`to_string`] read and write
+//! a whole file the way SEGA's converter does, with `1` and `0` for booleans.
+//!
+//! A section that isn't known here is kept in [`A3da::extra`], so it's written back the way it
+//! was read. A field that isn't known inside of a known section is an error instead of being
+//! dropped.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_divatree::a3da::{self, KeyType};
+//!
+//! let input = "#A3DA__________
+//! _.converter.version=20050823
+//! _.property.version=20050706
+//! curve.0.cv.type=1
+//! curve.0.cv.value=0.5
+//! curve.0.name=fade
+//! curve.length=1
+//! play_control.begin=0
+//! play_control.fps=60
+//! play_control.size=360
+//! ";
+//! let data = a3da::from_str(input).unwrap();
+//! assert_eq!(data.curve[0].cv.ty, KeyType::Static);
+//! assert_eq!(data.curve[0].cv.value, Some(0.5));
+//! assert_eq!(a3da::to_string(&data).unwrap(), input);
+//! ```
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::bools::BoolStyle;
+use crate::error::{DeserializerError, SerializerError};
+use crate::value::Value;
+
+/// The line SEGA's converter starts every file with.
+pub const MAGIC: &str = "#A3DA__________";
+
+/// Reads a whole A3DA file.
+pub fn from_str(s: &str) -> Result<A3da, DeserializerError> {
+    crate::from_str(s)
+}
+
+/// Writes `a3da` after the [`MAGIC`] line, with keys sorted and booleans as `1` and `0`.
+pub fn to_string(a3da: &A3da) -> Result<String, SerializerError> {
+    let mut buf = format!("{}\n", MAGIC).into_bytes();
+    let mut ser = crate::Serializer::new(&mut buf).with_bools(BoolStyle::OneZero);
+    a3da.serialize(&mut ser)?;
+    ser.finish()?;
+    Ok(String::from_utf8(buf).expect("the serializer only writes valid UTF-8"))
+}
+
+/// A whole A3DA file.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct A3da {
+    #[serde(rename = "_")]
+    pub header: Header,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub camera_root: Vec<CameraRoot>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub curve: Vec<Curve>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fog: Vec<Fog>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub light: Vec<Light>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub object: Vec<Object>,
+    /// The names of every [`Object`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub object_list: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objhrc: Vec<Objhrc>,
+    /// The names of every [`Objhrc`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objhrc_list: Vec<String>,
+    pub play_control: PlayControl,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub point: Vec<Point>,
+    /// Every section that isn't known, like `m_objhrc`, as it was written
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+impl<'de> Deserialize<'de> for A3da {
+    /// Reads the known sections, keeping the others in `extra`.
+    ///
+    /// `#[serde(flatten)]` isn't used for `extra`, for the same reason as in
+    /// [`crate::pv_db`].
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(A3daVisitor)
+    }
+}
+
+struct A3daVisitor;
+
+impl<'de> Visitor<'de> for A3daVisitor {
+    type Value = A3da;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an A3DA file")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut a3da = A3da::default();
+        let mut header = None;
+        let mut play_control = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "_" => header = Some(map.next_value()?),
+                "camera_root" => a3da.camera_root = map.next_value()?,
+                "curve" => a3da.curve = map.next_value()?,
+                "fog" => a3da.fog = map.next_value()?,
+                "light" => a3da.light = map.next_value()?,
+                "object" => a3da.object = map.next_value()?,
+                "object_list" => a3da.object_list = map.next_value()?,
+                "objhrc" => a3da.objhrc = map.next_value()?,
+                "objhrc_list" => a3da.objhrc_list = map.next_value()?,
+                "play_control" => play_control = Some(map.next_value()?),
+                "point" => a3da.point = map.next_value()?,
+                _ => {
+                    let value = map.next_value()?;
+                    a3da.extra.insert(key, value);
+                }
+            }
+        }
+        a3da.header = header.ok_or_else(|| de::Error::missing_field("_"))?;
+        a3da.play_control = play_control.ok_or_else(|| de::Error::missing_field("play_control"))?;
+        Ok(a3da)
+    }
+}
+
+/// The `_` node, saying which converter wrote the file.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Header {
+    pub converter: Version,
+    pub file_name: Option<String>,
+    pub property: Version,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Version {
+    pub version: u32,
+}
+
+/// The frames the animation plays.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlayControl {
+    pub begin: f32,
+    pub div: Option<u32>,
+    pub fps: f32,
+    pub offset: Option<f32>,
+    pub size: f32,
+}
+
+/// A camera, which looks from its `view_point` towards its `interest`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraRoot {
+    pub interest: ModelTransform,
+    pub model_transform: ModelTransform,
+    pub view_point: ViewPoint,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ViewPoint {
+    pub aspect: f32,
+    pub camera_aperture_h: Option<f32>,
+    pub camera_aperture_w: Option<f32>,
+    pub focal_length: Option<KeySet>,
+    /// The field of view in radians
+    pub fov: Option<KeySet>,
+    pub fov_is_horizontal: Option<bool>,
+    pub model_transform: ModelTransform,
+    pub roll: Option<KeySet>,
+}
+
+/// An animated model.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Object {
+    pub model_transform: ModelTransform,
+    pub morph: Option<String>,
+    pub morph_offset: Option<f32>,
+    pub name: String,
+    pub parent_name: Option<String>,
+    pub parent_node: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tex_pat: Vec<TexPat>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tex_transform: Vec<TexTransform>,
+    pub uid_name: String,
+}
+
+/// Swaps a texture for the ones of a pattern.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TexPat {
+    pub name: String,
+    pub pat: String,
+    pub pat_offset: f32,
+}
+
+/// Moves the UVs of a texture.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TexTransform {
+    pub coverage_u: Option<KeySet>,
+    pub coverage_v: Option<KeySet>,
+    pub name: String,
+    pub offset_u: Option<KeySet>,
+    pub offset_v: Option<KeySet>,
+    pub repeat_u: Option<KeySet>,
+    pub repeat_v: Option<KeySet>,
+    pub rotate: Option<KeySet>,
+    pub rotate_frame: Option<KeySet>,
+    pub translate_frame_u: Option<KeySet>,
+    pub translate_frame_v: Option<KeySet>,
+}
+
+/// An animated hierarchy of nodes, like the bones of a model.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Objhrc {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub node: Vec<Node>,
+    pub uid_name: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Node {
+    pub model_transform: ModelTransform,
+    pub name: String,
+    /// The index of the parent node, or `-1` for the root
+    pub parent: i32,
+}
+
+/// A single animated value, for anything that isn't a transform.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Curve {
+    pub cv: KeySet,
+    pub name: String,
+}
+
+/// An animated position without a model, like an effect's.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Point {
+    pub model_transform: ModelTransform,
+    pub name: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Light {
+    pub ambient: Option<Rgba>,
+    pub diffuse: Option<Rgba>,
+    pub id: u32,
+    pub incandescence: Option<Rgba>,
+    pub name: String,
+    pub position: Option<ModelTransform>,
+    pub specular: Option<Rgba>,
+    pub spot_direction: Option<ModelTransform>,
+    /// The kind of light, like `PARALLEL`
+    #[serde(rename = "type")]
+    pub ty: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fog {
+    pub density: Option<KeySet>,
+    pub diffuse: Option<Rgba>,
+    pub end: Option<KeySet>,
+    pub id: u32,
+    pub start: Option<KeySet>,
+}
+
+/// An animated color.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rgba {
+    pub a: Option<KeySet>,
+    pub b: KeySet,
+    pub g: KeySet,
+    pub r: KeySet,
+}
+
+/// The translation, rotation and scale of anything that can be moved.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelTransform {
+    pub rot: Vec3,
+    pub scale: Vec3,
+    pub trans: Vec3,
+    pub visibility: Option<KeySet>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Vec3 {
+    pub x: KeySet,
+    pub y: KeySet,
+    pub z: KeySet,
+}
+
+/// How a [`KeySet`] is animated, which is written as a number.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum KeyType {
+    /// Always `0`, so there's nothing else to the key set.
+    #[default]
+    None,
+    /// Always `value`.
+    Static,
+    /// Goes in a straight line from one key to the next.
+    Linear,
+    /// Follows the tangents of its keys.
+    Hermite,
+    /// Jumps from one key to the next.
+    Hold,
+}
+
+impl KeyType {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Self::None),
+            1 => Some(Self::Static),
+            2 => Some(Self::Linear),
+            3 => Some(Self::Hermite),
+            4 => Some(Self::Hold),
+            _ => None,
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Static => 1,
+            Self::Linear => 2,
+            Self::Hermite => 3,
+            Self::Hold => 4,
+        }
+    }
+}
+
+impl Serialize for KeyType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u8(self.as_u8())
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let v = u8::deserialize(deserializer)?;
+        Self::from_u8(v).ok_or_else(|| {
+            de::Error::invalid_value(
+                de::Unexpected::Unsigned(v.into()),
+                &"a key type from 0 to 4",
+            )
+        })
+    }
+}
+
+/// An animated value.
+///
+/// Depending on its [`KeyType`], it has a `value`, a list of `key`s, or the same keys packed
+/// into `raw_data`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeySet {
+    /// How the value carries on after the last key
+    pub ep_type_post: Option<u8>,
+    /// How the value carries on before the first key
+    pub ep_type_pre: Option<u8>,
+    pub key: Option<Vec<Key>>,
+    /// The last frame
+    pub max: Option<f32>,
+    pub raw_data: Option<RawData>,
+    /// The [`Key::ty`] shared by every key in `raw_data`
+    pub raw_data_key_type: Option<u8>,
+    #[serde(rename = "type")]
+    pub ty: KeyType,
+    pub value: Option<f32>,
+}
+
+/// A single keyframe.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Key {
+    /// The frame, followed by as many values as `ty` asks for.
+    #[serde(with = "tuple")]
+    pub data: Vec<f32>,
+    /// `0` for just a frame, `1` to add a value, `2` to add a tangent and `3` to add separate
+    /// incoming and outgoing tangents
+    #[serde(rename = "type")]
+    pub ty: u8,
+}
+
+impl Key {
+    pub fn frame(&self) -> f32 {
+        self.data.first().copied().unwrap_or_default()
+    }
+
+    pub fn value(&self) -> f32 {
+        self.data.get(1).copied().unwrap_or_default()
+    }
+}
+
+/// Keys packed into one line, in groups of the size [`KeySet::raw_data_key_type`] gives.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawData {
+    #[serde(with = "list")]
+    pub value_list: Vec<f32>,
+    pub value_list_size: usize,
+    /// The type of every value, which is always `float`
+    pub value_type: String,
+}
+
+/// A tuple like `(0, 1.5)`, or a number on its own when there's only one.
+mod tuple {
+    use super::*;
+
+    pub fn serialize<S>(data: &[f32], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match data {
+            [frame] => serializer.serialize_f32(*frame),
+            _ => {
+                let mut tuple = serializer.serialize_tuple(data.len())?;
+                for x in data {
+                    tuple.serialize_element(x)?;
+                }
+                tuple.end()
+            }
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<f32>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(TupleVisitor)
+    }
+
+    struct TupleVisitor;
+
+    impl<'de> Visitor<'de> for TupleVisitor {
+        type Value = Vec<f32>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a number or a tuple of numbers")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(4));
+            while let Some(x) = seq.next_element()? {
+                data.push(x);
+            }
+            Ok(data)
+        }
+
+        fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+            Ok(vec![v as f32])
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(vec![v as f32])
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(vec![v as f32])
+        }
+    }
+}
+
+/// A list like `0,1.5,2`, without parentheses.
+mod list {
+    use super::*;
+
+    pub fn serialize<S>(data: &[f32], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let list: Vec<_> = data.iter().map(f32::to_string).collect();
+        serializer.serialize_str(&list.join(","))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<f32>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let list = String::deserialize(deserializer)?;
+        crate::de::serde::atom::from_str(&list).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let input = "#A3DA__________
+_.converter.version=20050823
+_.file_name=CAMPV001_BASE.a3da
+_.property.version=20050706
+camera_root.0.interest.rot.x.type=0
+camera_root.0.interest.rot.y.type=0
+camera_root.0.interest.rot.z.type=0
+camera_root.0.interest.scale.x.type=1
+camera_root.0.interest.scale.x.value=1
+camera_root.0.interest.scale.y.type=1
+camera_root.0.interest.scale.y.value=1
+camera_root.0.interest.scale.z.type=1
+camera_root.0.interest.scale.z.value=1
+camera_root.0.interest.trans.x.key.0.data=(0, 0.5)
+camera_root.0.interest.trans.x.key.0.type=1
+camera_root.0.interest.trans.x.key.1.data=(60, 1, 0, 0.25)
+camera_root.0.interest.trans.x.key.1.type=3
+camera_root.0.interest.trans.x.key.length=2
+camera_root.0.interest.trans.x.max=60
+camera_root.0.interest.trans.x.type=3
+camera_root.0.interest.trans.y.ep_type_post=2
+camera_root.0.interest.trans.y.ep_type_pre=1
+camera_root.0.interest.trans.y.key.0.data=30
+camera_root.0.interest.trans.y.key.0.type=0
+camera_root.0.interest.trans.y.key.length=1
+camera_root.0.interest.trans.y.type=4
+camera_root.0.interest.trans.z.max=60
+camera_root.0.interest.trans.z.raw_data.value_list=0,1,30,2,60,3
+camera_root.0.interest.trans.z.raw_data.value_list_size=6
+camera_root.0.interest.trans.z.raw_data.value_type=float
+camera_root.0.interest.trans.z.raw_data_key_type=1
+camera_root.0.interest.trans.z.type=2
+camera_root.0.model_transform.rot.x.type=0
+camera_root.0.model_transform.rot.y.type=0
+camera_root.0.model_transform.rot.z.type=0
+camera_root.0.model_transform.scale.x.type=0
+camera_root.0.model_transform.scale.y.type=0
+camera_root.0.model_transform.scale.z.type=0
+camera_root.0.model_transform.trans.x.type=0
+camera_root.0.model_transform.trans.y.type=0
+camera_root.0.model_transform.trans.z.type=0
+camera_root.0.view_point.aspect=1.777778
+camera_root.0.view_point.fov.type=1
+camera_root.0.view_point.fov.value=0.93616
+camera_root.0.view_point.fov_is_horizontal=1
+camera_root.0.view_point.model_transform.rot.x.type=0
+camera_root.0.view_point.model_transform.rot.y.type=0
+camera_root.0.view_point.model_transform.rot.z.type=0
+camera_root.0.view_point.model_transform.scale.x.type=0
+camera_root.0.view_point.model_transform.scale.y.type=0
+camera_root.0.view_point.model_transform.scale.z.type=0
+camera_root.0.view_point.model_transform.trans.x.type=0
+camera_root.0.view_point.model_transform.trans.y.type=0
+camera_root.0.view_point.model_transform.trans.z.type=0
+camera_root.0.view_point.roll.type=0
+camera_root.length=1
+fog.0.density.type=1
+fog.0.density.value=0.1
+fog.0.id=0
+fog.length=1
+light.0.diffuse.b.type=1
+light.0.diffuse.b.value=1
+light.0.diffuse.g.type=1
+light.0.diffuse.g.value=1
+light.0.diffuse.r.type=1
+light.0.diffuse.r.value=1
+light.0.id=1
+light.0.name=chara
+light.0.type=PARALLEL
+light.length=1
+object.0.model_transform.rot.x.type=0
+object.0.model_transform.rot.y.type=0
+object.0.model_transform.rot.z.type=0
+object.0.model_transform.scale.x.type=0
+object.0.model_transform.scale.y.type=0
+object.0.model_transform.scale.z.type=0
+object.0.model_transform.trans.x.type=0
+object.0.model_transform.trans.y.type=0
+object.0.model_transform.trans.z.type=0
+object.0.model_transform.visibility.type=1
+object.0.model_transform.visibility.value=1
+object.0.name=STGPV001_EFF
+object.0.tex_pat.0.name=EFF_TEX
+object.0.tex_pat.0.pat=EFF_TEX_%03d
+object.0.tex_pat.0.pat_offset=0
+object.0.tex_pat.length=1
+object.0.tex_transform.0.name=EFF_UV
+object.0.tex_transform.0.offsetU.type=1
+object.0.tex_transform.0.offsetU.value=0.5
+object.0.tex_transform.length=1
+object.0.uid_name=STGPV001_EFF
+object.length=1
+object_list.0=STGPV001_EFF
+object_list.length=1
+play_control.begin=0
+play_control.fps=60
+play_control.size=3600
+";
+        let data = from_str(input).unwrap();
+        let camera = &data.camera_root[0];
+        assert_eq!(data.header.converter.version, 20050823);
+        assert_eq!(camera.view_point.fov_is_horizontal, Some(true));
+        assert_eq!(camera.interest.trans.x.ty, KeyType::Hermite);
+        let keys = camera.interest.trans.x.key.as_ref().unwrap();
+        assert_eq!(keys[1].frame(), 60.0);
+        assert_eq!(keys[1].data, [60.0, 1.0, 0.0, 0.25]);
+        assert_eq!(
+            camera.interest.trans.y.key.as_ref().unwrap()[0].data,
+            [30.0]
+        );
+        let raw = camera.interest.trans.z.raw_data.as_ref().unwrap();
+        assert_eq!(raw.value_list, [0.0, 1.0, 30.0, 2.0, 60.0, 3.0]);
+        assert_eq!(
+            data.object[0].tex_transform[0]
+                .offset_u
+                .as_ref()
+                .unwrap()
+                .value,
+            Some(0.5)
+        );
+        assert_eq!(data.light[0].ty.as_deref(), Some("PARALLEL"));
+        assert_eq!(to_string(&data).unwrap(), input);
+    }
+
+    #[test]
+    fn round_trip_unknown_sections() {
+        let input = "#A3DA__________
+_.converter.version=20050823
+_.property.version=20050706
+camera_auxiliary.exposure.type=1
+camera_auxiliary.exposure.value=1.00
+m_objhrc.0.name=CMNITM1001
+m_objhrc.length=1
+play_control.begin=0
+play_control.fps=60
+play_control.size=360
+";
+        let data = from_str(input).unwrap();
+        assert_eq!(
+            data.extra["camera_auxiliary"]["exposure"]["value"].as_str(),
+            Some("1.00")
+        );
+        assert!(data.extra.contains_key("m_objhrc"));
+        assert_eq!(to_string(&data).unwrap(), input);
+    }
+
+    #[test]
+    fn read_unknown_field() {
+        let err = from_str(
+            "_.converter.version=1\n_.property.version=1\ncurve.0.cv.type=0\ncurve.0.foo=1\n",
+        )
+        .unwrap_err();
+        assert_eq!(err.path(), Some("curve.0.foo"));
+        let source = std::error::Error::source(&err).unwrap().to_string();
+        assert!(source.starts_with("unknown field `foo`"), "{}", source);
+    }
+
+    #[test]
+    fn read_key_type() {
+        let err = from_str("_.converter.version=1\n_.property.version=1\ncurve.0.cv.type=5\n")
+            .unwrap_err();
+        assert_eq!(err.path(), Some("curve.0.cv.type"));
+    }
+}
@@ -0,0 +1,132 @@
+//! The spellings of `true` and `false`.
+//!
+//! [`crate::Options::with_bools`] decides which spellings are read into a `bool`, and
+//! [`crate::Serializer::with_bools`] which one is written. Both only apply when the type is
+//! known to be a `bool` though. Types that buffer their fields, like ones with
+//! `#[serde(flatten)]`, see `1` as an integer instead, so [`deserialize`] reads any spelling
+//! no matter how it's seen.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_derive::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct ViewPoint {
+//!     #[serde(flatten)]
+//!     fov: Fov,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Fov {
+//!     #[serde(deserialize_with = "serde_divatree::bools::deserialize")]
+//!     fov_is_horizontal: bool,
+//! }
+//!
+//! let view_point: ViewPoint = serde_divatree::from_str("fov_is_horizontal=1").unwrap();
+//! assert!(view_point.fov.fov_is_horizontal);
+//! ```
+use std::fmt;
+
+use serde::de::{self, Deserializer, Unexpected, Visitor};
+
+/// A way of spelling `true` and `false`.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum BoolStyle {
+    /// `true` and `false`
+    #[default]
+    TrueFalse,
+    /// `1` and `0`, like `fov_is_horizontal=1`
+    OneZero,
+    /// `on` and `off`
+    OnOff,
+    /// `yes` and `no`
+    YesNo,
+}
+
+impl BoolStyle {
+    /// Every style, which is what [`crate::Options`] reads by default.
+    pub const ALL: &'static [Self] = &[Self::TrueFalse, Self::OneZero, Self::OnOff, Self::YesNo];
+
+    /// How `v` is spelled in this style.
+    pub fn spell(self, v: bool) -> &'static str {
+        match (self, v) {
+            (Self::TrueFalse, true) => "true",
+            (Self::TrueFalse, false) => "false",
+            (Self::OneZero, true) => "1",
+            (Self::OneZero, false) => "0",
+            (Self::OnOff, true) => "on",
+            (Self::OnOff, false) => "off",
+            (Self::YesNo, true) => "yes",
+            (Self::YesNo, false) => "no",
+        }
+    }
+
+    /// Reads `s` if it's spelled in this style, ignoring case.
+    pub fn parse(self, s: &str) -> Option<bool> {
+        [true, false]
+            .iter()
+            .copied()
+            .find(|x| s.eq_ignore_ascii_case(self.spell(*x)))
+    }
+}
+
+/// Reads a `bool` spelled in any [`BoolStyle`], even when it was already read as an integer.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(BoolVisitor)
+}
+
+struct BoolVisitor;
+
+impl<'de> Visitor<'de> for BoolVisitor {
+    type Value = bool;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a boolean")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<bool, E> {
+        Ok(v)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<bool, E> {
+        match v {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(E::invalid_value(Unexpected::Signed(v), &self)),
+        }
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<bool, E> {
+        match v {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(E::invalid_value(Unexpected::Unsigned(v), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<bool, E> {
+        BoolStyle::ALL
+            .iter()
+            .find_map(|x| x.parse(v))
+            .ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spell_bools() {
+        for style in BoolStyle::ALL {
+            assert_eq!(style.parse(style.spell(true)), Some(true));
+            assert_eq!(style.parse(style.spell(false)), Some(false));
+        }
+        assert_eq!(BoolStyle::YesNo.parse("YES"), Some(true));
+        assert_eq!(BoolStyle::OnOff.parse("1"), None);
+    }
+}
@@ -0,0 +1,564 @@
+//! A lossless view of a file, for editing it in place.
+//!
+//! Unlike [`Value`], a [`Document`] keeps every line as it was written, including comments,
+//! blank lines, the whitespace around `=` and the order of the entries. Only the lines that
+//! are edited change when it's written back.
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::ops::{Bound, Range};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::de::{KeyValue, Options, Source};
+use crate::error::{DeserializerError, SerializerError};
+use crate::radix::{self, Int, Radix};
+use crate::ser::atom;
+use crate::value::Value;
+
+/// A single line of a [`Document`].
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Line {
+    /// A line containing nothing but whitespace.
+    Blank(String),
+    /// A line starting with `#`.
+    Comment(String),
+    Entry(Entry),
+    /// A line that isn't a key value pair.
+    Malformed(String),
+}
+
+impl Line {
+    fn parse(line: &str) -> Self {
+        if line.trim().is_empty() {
+            Self::Blank(line.to_string())
+        } else if line.starts_with('#') {
+            Self::Comment(line.to_string())
+        } else {
+            Entry::parse(line).map_or_else(|| Self::Malformed(line.to_string()), Self::Entry)
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Blank(line) | Self::Comment(line) | Self::Malformed(line) => line,
+            Self::Entry(entry) => entry.as_str(),
+        }
+    }
+
+    pub fn as_entry(&self) -> Option<&Entry> {
+        match self {
+            Self::Entry(entry) => Some(entry),
+            _ => None,
+        }
+    }
+}
+
+/// A `key=value` line, along with the whitespace around its key and value.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Entry {
+    line: String,
+    key: Range<usize>,
+    value: Range<usize>,
+}
+
+impl Entry {
+    fn parse(line: &str) -> Option<Self> {
+        let kv = KeyValue::new(line)?;
+        Some(Self {
+            line: line.to_string(),
+            key: kv.key_range(),
+            value: kv.value_range(),
+        })
+    }
+
+    fn new(key: &str, value: &str) -> Self {
+        Self {
+            line: format!("{}={}", key, value),
+            key: 0..key.len(),
+            value: key.len() + 1..key.len() + 1 + value.len(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.line[self.key.clone()]
+    }
+
+    pub fn value(&self) -> &str {
+        &self.line[self.value.clone()]
+    }
+
+    /// Replaces the value, keeping the whitespace around it.
+    fn set_value(&mut self, value: &str) -> String {
+        let old = self.value().to_string();
+        self.line.replace_range(self.value.clone(), value);
+        self.value.end = self.value.start + value.len();
+        old
+    }
+
+    /// Replaces the key, keeping the whitespace around it.
+    fn set_key(&mut self, key: &str) {
+        let old_end = self.key.end;
+        let end = self.key.start + key.len();
+        self.line.replace_range(self.key.clone(), key);
+        self.key.end = end;
+        self.value = self.value.start - old_end + end..self.value.end - old_end + end;
+    }
+
+    /// Whether this entry is `path` or one of its children.
+    pub fn is_under(&self, path: &str) -> bool {
+        let key = self.key();
+        key.strip_prefix(path)
+            .is_some_and(|x| x.is_empty() || x.starts_with(KeyValue::PATH_DELIMITER))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.line
+    }
+}
+
+/// Every line of a file.
+///
+/// # Example
+///
+/// ```
+/// use serde_divatree::cst::Document;
+///
+/// let input = "# comment
+/// pv_001.bpm = 120
+///
+/// pv_001.song_name = Foo
+/// ";
+/// let mut doc: Document = input.parse().unwrap();
+/// doc.set("pv_001.bpm", &150).unwrap();
+/// doc.set("pv_001.date", &20090702).unwrap();
+/// assert_eq!(
+///     doc.to_string(),
+///     "# comment
+/// pv_001.bpm = 150
+/// pv_001.date=20090702
+///
+/// pv_001.song_name = Foo
+/// "
+/// );
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Document {
+    lines: Vec<Line>,
+    newline: &'static str,
+    trailing_newline: bool,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self {
+            lines: Vec::new(),
+            newline: "\n",
+            trailing_newline: true,
+        }
+    }
+}
+
+impl Document {
+    pub fn parse(input: &str) -> Self {
+        Self {
+            lines: input.lines().map(Line::parse).collect(),
+            newline: if input.contains("\r\n") { "\r\n" } else { "\n" },
+            trailing_newline: input.is_empty() || input.ends_with('\n'),
+        }
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.lines.iter().filter_map(Line::as_entry)
+    }
+
+    fn entries_mut(&mut self) -> impl Iterator<Item = &mut Entry> {
+        self.lines.iter_mut().filter_map(|x| match x {
+            Line::Entry(entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    /// The value of the first entry with exactly `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries().find(|x| x.key() == key).map(Entry::value)
+    }
+
+    /// The index in [`Document::lines`] of the first entry with exactly `key`.
+    pub fn position(&self, key: &str) -> Option<usize> {
+        self.lines
+            .iter()
+            .position(|x| x.as_entry().is_some_and(|x| x.key() == key))
+    }
+
+    /// Where the line at `index` is in the written document, without its newline.
+    pub fn span(&self, index: usize) -> Range<usize> {
+        let start: usize = self.lines[..index]
+            .iter()
+            .map(|x| x.as_str().len() + self.newline.len())
+            .sum();
+        start..start + self.lines[index].as_str().len()
+    }
+
+    /// The [`Document::span`] of every line, in one pass.
+    pub(crate) fn spans(&self) -> Vec<Range<usize>> {
+        let mut start = 0;
+        self.lines
+            .iter()
+            .map(|x| {
+                let span = start..start + x.as_str().len();
+                start = span.end + self.newline.len();
+                span
+            })
+            .collect()
+    }
+
+    /// Sets the value of `key`, returning the previous value if there was one.
+    ///
+    /// Existing entries are edited in place, and an integer replacing one written with a
+    /// `0x`, `0o` or `0b` prefix keeps that prefix. New entries are inserted after the last
+    /// entry sorting before them, so sorted files stay sorted.
+    pub fn set<T>(&mut self, key: &str, value: &T) -> Result<Option<String>, SerializerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut value = atom::to_string(value)?;
+        let radix = self.get(key).map_or(Radix::Decimal, Radix::of);
+        if let Ok((int, Radix::Decimal)) = radix::parse::<i128>(&value) {
+            if radix != Radix::Decimal {
+                value = Int::new(int, radix).to_string();
+            }
+        }
+        self.set_raw(key, &value)
+    }
+
+    /// Sets the value of `key` to exactly `value`, which has to be a valid atom.
+    pub(crate) fn set_raw(
+        &mut self,
+        key: &str,
+        value: &str,
+    ) -> Result<Option<String>, SerializerError> {
+        atom::check_str(value)?;
+        if let Some(entry) = self.entries_mut().find(|x| x.key() == key) {
+            return Ok(Some(entry.set_value(value)));
+        }
+        check_key(key)?;
+        self.insert(Entry::new(key, value));
+        Ok(None)
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        let after = self
+            .lines
+            .iter()
+            .rposition(|x| matches!(x, Line::Entry(x) if x.key() < entry.key()));
+        let first = self.lines.iter().position(|x| x.as_entry().is_some());
+        let index = match (after, first) {
+            (Some(after), _) => after + 1,
+            (None, Some(first)) => first,
+            (None, None) => self.lines.len(),
+        };
+        self.lines.insert(index, Line::Entry(entry));
+    }
+
+    /// Removes every entry with exactly `key`, returning the value of the first one.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let mut removed = None;
+        self.lines.retain(|x| match x {
+            Line::Entry(entry) if entry.key() == key => {
+                removed.get_or_insert_with(|| entry.value().to_string());
+                false
+            }
+            _ => true,
+        });
+        removed
+    }
+
+    /// Removes `path` and everything under it, returning how many entries were removed.
+    pub fn remove_all(&mut self, path: &str) -> usize {
+        let len = self.lines.len();
+        self.lines
+            .retain(|x| !matches!(x, Line::Entry(entry) if entry.is_under(path)));
+        len - self.lines.len()
+    }
+
+    /// Renames every entry that `rename` returns a new key for.
+    pub(crate) fn rename(&mut self, mut rename: impl FnMut(&str) -> Option<String>) {
+        for entry in self.entries_mut() {
+            if let Some(key) = rename(entry.key()) {
+                entry.set_key(&key);
+            }
+        }
+    }
+
+    /// Builds a [`Value`] out of the entries, ignoring everything else.
+    pub fn to_value(&self) -> Result<Value, DeserializerError> {
+        let lines = Options::default().lines(self.entries().map(Entry::as_str), Source::Unknown)?;
+        Value::from_lines(lines, Source::Unknown)
+    }
+}
+
+/// Checks that `key` is read back as it is from the left of `=`, which the serializer checks
+/// for each part of the path.
+fn check_key(key: &str) -> Result<(), SerializerError> {
+    if key.is_empty()
+        || key.contains('=')
+        || key.contains('\n')
+        || key.trim() != key
+        || key.starts_with('#')
+    {
+        return Err(SerializerError::InvalidKey(key.to_string()));
+    }
+    Ok(())
+}
+
+/// Every key of `map` that's `path` or under it.
+fn keys_under<V>(map: &BTreeMap<String, V>, path: &str) -> Vec<String> {
+    let prefix = format!("{}{}", path, KeyValue::PATH_DELIMITER);
+    let children = map
+        .range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+        .map(|(key, _)| key)
+        .take_while(|key| key.starts_with(&prefix));
+    map.get_key_value(path)
+        .map(|(key, _)| key)
+        .into_iter()
+        .chain(children)
+        .cloned()
+        .collect()
+}
+
+/// Makes many edits to a [`Document`] without going through its lines for each of them.
+///
+/// Keys are looked up in an index of the entries, and new entries are only put in place by
+/// [`Editor::finish`], after the last entry sorting before them like [`Document::set`] does.
+pub(crate) struct Editor {
+    doc: Document,
+    /// The lines of every entry, by key
+    lines: BTreeMap<String, Vec<usize>>,
+    /// The entries that aren't in the document yet
+    added: BTreeMap<String, String>,
+    /// Whether each line is removed
+    removed: Vec<bool>,
+}
+
+impl Editor {
+    pub(crate) fn new(doc: Document) -> Self {
+        let mut lines: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (i, line) in doc.lines.iter().enumerate() {
+            if let Line::Entry(entry) = line {
+                lines.entry(entry.key().to_string()).or_default().push(i);
+            }
+        }
+        Self {
+            removed: vec![false; doc.lines.len()],
+            doc,
+            lines,
+            added: BTreeMap::new(),
+        }
+    }
+
+    /// The value of the first entry with exactly `key`.
+    pub(crate) fn get(&self, key: &str) -> Option<&str> {
+        match self.lines.get(key) {
+            Some(lines) => self.doc.lines[lines[0]].as_entry().map(Entry::value),
+            None => self.added.get(key).map(String::as_str),
+        }
+    }
+
+    /// Whether there are entries under `path`, not counting `path` itself.
+    pub(crate) fn has_children(&self, path: &str) -> bool {
+        let prefix = format!("{}{}", path, KeyValue::PATH_DELIMITER);
+        let bounds = (Bound::Included(prefix.as_str()), Bound::Unbounded);
+        let first = [
+            self.lines
+                .range::<str, _>(bounds)
+                .next()
+                .map(|(key, _)| key),
+            self.added
+                .range::<str, _>(bounds)
+                .next()
+                .map(|(key, _)| key),
+        ];
+        first.iter().flatten().any(|key| key.starts_with(&prefix))
+    }
+
+    /// Sets the value of `key`, adding an entry for it if there's none.
+    pub(crate) fn set(&mut self, key: &str, value: &str) -> Result<(), SerializerError> {
+        atom::check_str(value)?;
+        if let Some(lines) = self.lines.get(key) {
+            if let Line::Entry(entry) = &mut self.doc.lines[lines[0]] {
+                entry.set_value(value);
+            }
+            return Ok(());
+        }
+        check_key(key)?;
+        self.added.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    /// Removes `path` and everything under it.
+    pub(crate) fn remove_all(&mut self, path: &str) {
+        for key in keys_under(&self.lines, path) {
+            for line in self.lines.remove(&key).unwrap_or_default() {
+                self.removed[line] = true;
+            }
+        }
+        for key in keys_under(&self.added, path) {
+            self.added.remove(&key);
+        }
+    }
+
+    /// Puts every new entry in place and returns the edited document.
+    pub(crate) fn finish(self) -> Document {
+        let Self {
+            mut doc,
+            lines,
+            added,
+            removed,
+        } = self;
+        // The line each new entry goes after, with `None` for before the first entry
+        let mut after: BTreeMap<Option<usize>, Vec<Line>> = BTreeMap::new();
+        for (key, value) in added {
+            let line = lines
+                .range::<str, _>((Bound::Unbounded, Bound::Excluded(key.as_str())))
+                .next_back()
+                .and_then(|(_, lines)| lines.last().copied());
+            after
+                .entry(line)
+                .or_default()
+                .push(Line::Entry(Entry::new(&key, &value)));
+        }
+        let first = lines.values().filter_map(|x| x.first()).min().copied();
+        let mut before_first = after.remove(&None).unwrap_or_default();
+        let old = std::mem::take(&mut doc.lines);
+        for (i, line) in old.into_iter().enumerate() {
+            if Some(i) == first {
+                doc.lines.append(&mut before_first);
+            }
+            if !removed[i] {
+                doc.lines.push(line);
+            }
+            if let Some(mut entries) = after.remove(&Some(i)) {
+                doc.lines.append(&mut entries);
+            }
+        }
+        // There were no entries to go before
+        doc.lines.append(&mut before_first);
+        doc
+    }
+}
+
+impl FromStr for Document {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(s))
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_str(self.newline)?;
+            }
+            f.write_str(line.as_str())?;
+        }
+        if self.trailing_newline && !self.lines.is_empty() {
+            f.write_str(self.newline)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "# pv_db made by hand
+pv_001.bpm = 120
+pv_001.difficulty.easy.0.level=PV_LV_03_0
+pv_001.difficulty.easy.length=1
+
+# the second pv
+pv_002.bpm=180
+pv_002.song_name = 	Bar
+";
+
+    #[test]
+    fn read_document() {
+        let doc = Document::parse(INPUT);
+        assert_eq!(doc.to_string(), INPUT);
+        assert_eq!(doc.lines().len(), 8);
+        assert_eq!(doc.entries().count(), 5);
+        assert_eq!(doc.get("pv_002.song_name"), Some("Bar"));
+        assert_eq!(doc.get("pv_002"), None);
+
+        let crlf = INPUT.replace('\n', "\r\n");
+        assert_eq!(Document::parse(&crlf).to_string(), crlf);
+        let unterminated = INPUT.trim_end();
+        assert_eq!(Document::parse(unterminated).to_string(), unterminated);
+    }
+
+    #[test]
+    fn edit_document() {
+        let mut doc = Document::parse(INPUT);
+        assert_eq!(
+            doc.set("pv_002.song_name", "Baz").unwrap(),
+            Some("Bar".into())
+        );
+        assert_eq!(doc.set("pv_001.date", &20090702).unwrap(), None);
+        assert_eq!(doc.set("pv_000.bpm", &60).unwrap(), None);
+        assert_eq!(doc.set("pv_003.bpm", &90).unwrap(), None);
+        assert_eq!(doc.remove("pv_001.bpm"), Some("120".into()));
+        let expected = "# pv_db made by hand
+pv_000.bpm=60
+pv_001.date=20090702
+pv_001.difficulty.easy.0.level=PV_LV_03_0
+pv_001.difficulty.easy.length=1
+
+# the second pv
+pv_002.bpm=180
+pv_002.song_name = 	Baz
+pv_003.bpm=90
+";
+        assert_eq!(doc.to_string(), expected);
+
+        assert_eq!(doc.remove_all("pv_001.difficulty"), 2);
+        assert_eq!(doc.remove_all("pv_00"), 0);
+        assert_eq!(doc.entries().count(), 5);
+        assert!(doc.set("foo=bar", &1).is_err());
+        assert!(doc.set("#foo", &1).is_err());
+        assert!(doc.set("pv_001.bpm", "1=2").is_err());
+        assert!(doc.set("pv_002.song_name", " Foo").is_err());
+        assert_eq!(doc.get("pv_002.song_name"), Some("Baz"));
+        let mut editor = Editor::new(Document::parse("a=1\n"));
+        assert!(editor.set("#b", "1").is_err());
+        assert!(editor.set("a", "x=y").is_err());
+        assert_eq!(editor.finish().to_string(), "a=1\n");
+
+        let mut doc = Document::parse("script_format=0x14012316\nversion=0b1\n");
+        doc.set("script_format", &0x14012317).unwrap();
+        doc.set("version", "2").unwrap();
+        assert_eq!(doc.to_string(), "script_format=0x14012317\nversion=0b10\n");
+        doc.set("version", "two").unwrap();
+        assert_eq!(doc.get("version"), Some("two"));
+    }
+
+    #[test]
+    fn document_to_value() {
+        let doc = Document::parse(INPUT);
+        let value = doc.to_value().unwrap();
+        assert_eq!(
+            value["pv_001.difficulty.easy"]
+                .as_seq()
+                .map(crate::value::Seq::len),
+            Some(1)
+        );
+        assert_eq!(value["pv_002.bpm"].as_str(), Some("180"));
+    }
+}
@@ -1,12 +1,19 @@
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::iter::{Peekable, Take};
 use std::num::NonZeroU8;
 use std::ops::Range;
+use std::rc::Rc;
 use std::str::{Lines, Split};
 
 pub mod serde;
-pub use self::serde::from_str;
+pub use self::serde::{
+    from_lines, from_reader, from_slice, from_str, from_str_named, from_str_recovering,
+    DuplicateKeys, Inference, LengthCheck, Options,
+};
 
-use crate::error::ParseError;
+use crate::error::{DeserializerError, ErrorKind, Location, ParseError};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
 struct Lexer<I> {
@@ -14,17 +21,17 @@ struct Lexer<I> {
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
-struct KeyValue<'a> {
-    key: &'a str,
-    value: &'a str,
+pub(crate) struct KeyValue<'a> {
+    pub(crate) key: &'a str,
+    pub(crate) value: &'a str,
     orig: &'a str,
 }
 
 impl<'a> KeyValue<'a> {
-    const PATH_DELIMITER: char = '.';
+    pub(crate) const PATH_DELIMITER: char = '.';
     const KEY_VALUE_DELIMITER: char = '=';
 
-    fn new(line: &'a str) -> Option<Self> {
+    pub(crate) fn new(line: &'a str) -> Option<Self> {
         let mut iter = line.split(Self::KEY_VALUE_DELIMITER);
         let key = iter.next()?.trim();
         let value = iter.next()?.trim();
@@ -34,15 +41,15 @@ impl<'a> KeyValue<'a> {
             orig: line,
         })
     }
-    fn key_range(&self) -> Range<usize> {
+    pub(crate) fn key_range(&self) -> Range<usize> {
         let start = self.orig.find(self.key).unwrap();
         start..start + self.key.len()
     }
-    fn value_range(&self) -> Range<usize> {
+    pub(crate) fn value_range(&self) -> Range<usize> {
         let start = self.orig.rfind(self.value).unwrap();
         start..start + self.value.len()
     }
-    fn path(&self) -> impl Iterator<Item = &'a str> {
+    pub(crate) fn path(&self) -> impl Iterator<Item = &'a str> {
         self.key
             .split(Self::PATH_DELIMITER)
             .filter(|x| !x.is_empty())
@@ -181,6 +188,190 @@ where
     }
 }
 
+/// A node whose children don't all follow each other.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct ScatteredKey {
+    /// The key path of the node. e.g. `pv_001.difficulty`
+    pub path: String,
+    /// The bytes of the line where the node shows up again.
+    pub span: Range<usize>,
+}
+
+/// Finds every node in `input` whose children are split up by other lines.
+///
+/// The parser expects children to be contiguous, so these need [`Options::with_unordered`].
+pub fn scattered_keys(input: &str) -> Vec<ScatteredKey> {
+    find_scattered(input.lines(), Source::Str(input))
+}
+
+pub(crate) fn find_scattered<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    source: Source<'a>,
+) -> Vec<ScatteredKey> {
+    let mut closed = HashSet::new();
+    let mut reported = HashSet::new();
+    let mut scattered = Vec::new();
+    let mut prev: Vec<&str> = Vec::new();
+    for line in lines {
+        let kv = match KeyValue::new(line) {
+            Some(kv) if !line.starts_with('#') => kv,
+            _ => continue,
+        };
+        let cur: Vec<_> = kv.path().collect();
+        // Only the proper prefixes of a key are nodes; the key itself is a leaf
+        let common = prev
+            .iter()
+            .zip(&cur)
+            .take(prev.len().min(cur.len()).saturating_sub(1))
+            .take_while(|(a, b)| a == b)
+            .count();
+        for level in common + 1..prev.len() {
+            closed.insert(prev[..level].join("."));
+        }
+        for level in common + 1..cur.len() {
+            let path = cur[..level].join(".");
+            if closed.contains(&path) && reported.insert(path.clone()) {
+                scattered.push(ScatteredKey {
+                    path,
+                    span: source.span(line),
+                });
+            }
+        }
+        prev = cur;
+    }
+    scattered
+}
+
+/// Stably sorts `lines` by their keys, so every node's children follow each other.
+pub(crate) fn group_lines<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut lines: Vec<_> = lines.collect();
+    lines.sort_by_key(|x| KeyValue::new(x).map_or(*x, |kv| kv.key));
+    lines
+}
+
+/// Keeps only the last line of every key, in place of the earlier ones.
+pub(crate) fn keep_last(lines: Vec<&str>) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    let mut lines: Vec<_> = lines
+        .into_iter()
+        .rev()
+        .filter(|x| KeyValue::new(x).map_or(true, |kv| seen.insert(kv.key)))
+        .collect();
+    lines.reverse();
+    lines
+}
+
+/// Where the lines given to the parser come from, to find their byte offsets in the input.
+#[derive(Clone, Copy)]
+pub(crate) enum Source<'a> {
+    /// Every line is a slice of this string.
+    Str(&'a str),
+    Unknown,
+}
+
+impl<'a> Source<'a> {
+    /// The offset of `s` in bytes since the beginning of the input.
+    pub(crate) fn offset(&self, s: &str) -> Option<usize> {
+        match self {
+            Self::Str(input) => {
+                let start = input.as_ptr() as usize;
+                let ptr = s.as_ptr() as usize;
+                (start..=start + input.len())
+                    .contains(&ptr)
+                    .then(|| ptr - start)
+            }
+            Self::Unknown => None,
+        }
+    }
+
+    /// The line and column of `byte`.
+    pub(crate) fn location(&self, byte: usize) -> Option<Location> {
+        match self {
+            Self::Str(input) => Some(Location::new(input, byte)),
+            Self::Unknown => None,
+        }
+    }
+
+    /// The bytes `s` covers in the input, or an empty range if it isn't part of it.
+    pub(crate) fn span(&self, s: &str) -> Range<usize> {
+        self.offset(s)
+            .map_or_else(Range::default, |start| start..start + s.len())
+    }
+}
+
+/// Applies a [`DuplicateKeys`] policy to lines as they're read.
+///
+/// [`DuplicateKeys::LastWins`] needs every line up front, so those lines have to go through
+/// [`keep_last`] first. The first duplicate found ends the lines and is put in `error`.
+pub(crate) struct Deduplicate<'a, I> {
+    lines: I,
+    /// The first line of every key so far
+    seen: HashMap<&'a str, &'a str>,
+    policy: DuplicateKeys,
+    source: Source<'a>,
+    error: Rc<RefCell<Option<DeserializerError>>>,
+}
+
+impl<'a, I> Deduplicate<'a, I> {
+    pub(crate) fn new(
+        lines: I,
+        policy: DuplicateKeys,
+        source: Source<'a>,
+        error: Rc<RefCell<Option<DeserializerError>>>,
+    ) -> Self {
+        Self {
+            lines,
+            seen: HashMap::new(),
+            policy,
+            source,
+            error,
+        }
+    }
+
+    fn key_span(&self, line: &str, kv: &KeyValue) -> Range<usize> {
+        let start = self.source.offset(line).unwrap_or_default();
+        let key = kv.key_range();
+        start + key.start..start + key.end
+    }
+}
+
+impl<'a, I> Iterator for Deduplicate<'a, I>
+where
+    I: Iterator<Item = &'a str>,
+{
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?;
+            let kv = match KeyValue::new(line) {
+                Some(kv) => kv,
+                None => return Some(line),
+            };
+            let first = match self.seen.entry(kv.key) {
+                Entry::Vacant(entry) => {
+                    entry.insert(line);
+                    return Some(line);
+                }
+                Entry::Occupied(entry) => *entry.get(),
+            };
+            match self.policy {
+                DuplicateKeys::Error => {
+                    let err = ErrorKind::DuplicateKey {
+                        key: kv.key.to_string(),
+                        first: self.key_span(first, &KeyValue::new(first)?),
+                        second: self.key_span(line, &kv),
+                    };
+                    *self.error.borrow_mut() = Some(err.into());
+                    return None;
+                }
+                DuplicateKeys::FirstWins => continue,
+                DuplicateKeys::LastWins => return Some(line),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -312,4 +503,41 @@ test = 1
         assert_eq!(iter.byte_offset, 33..42);
         assert_eq!(&INPUT[iter.byte_offset.clone()], "test = 1\n");
     }
+
+    #[test]
+    fn scattered() {
+        const INPUT: &'static str = "pv_001.song_name=Foo
+pv_001.difficulty.easy.0.level=1
+pv_002.song_name=Bar
+pv_001.bpm=120
+pv_001.difficulty.easy.length=1
+pv_002.bpm=180
+";
+        let scattered = scattered_keys(INPUT);
+        assert_eq!(
+            scattered,
+            vec![
+                ScatteredKey {
+                    path: "pv_001".into(),
+                    span: 75..89,
+                },
+                ScatteredKey {
+                    path: "pv_001.difficulty".into(),
+                    span: 90..121,
+                },
+                ScatteredKey {
+                    path: "pv_001.difficulty.easy".into(),
+                    span: 90..121,
+                },
+                ScatteredKey {
+                    path: "pv_002".into(),
+                    span: 122..136,
+                },
+            ]
+        );
+        assert_eq!(&INPUT[scattered[0].span.clone()], "pv_001.bpm=120");
+
+        let sorted = group_lines(INPUT.lines()).join("\n");
+        assert_eq!(scattered_keys(&sorted), vec![]);
+    }
 }
@@ -1,18 +1,22 @@
 use std::{
+    cell::RefCell,
     fmt::Display,
     num::{ParseFloatError, ParseIntError},
     ops::Range,
+    rc::Rc,
     str::{FromStr, Split},
 };
 
 #[cfg(feature = "miette")]
 use miette::Diagnostic;
 use serde::{
-    de::{SeqAccess, Visitor},
+    de::{DeserializeSeed, EnumAccess, IntoDeserializer, SeqAccess, VariantAccess, Visitor},
     Deserialize, Deserializer,
 };
 use thiserror::Error;
 
+use super::{Inference, Options};
+use crate::radix::{self, Integer, Radix};
 use crate::DeserializerError;
 
 pub fn from_str<'a, T>(input: &'a str) -> Result<T, ParseAtomError>
@@ -20,14 +24,26 @@ where
     T: Deserialize<'a>,
 {
     let span = 0..input.len();
-    let mut lex = AtomParser { input, span };
+    let mut lex = AtomParser {
+        input,
+        span,
+        options: Options::default(),
+        skipped: None,
+    };
     T::deserialize(lex)
 }
 
+/// Where atoms that couldn't be read are put while recovering from errors.
+pub(crate) type Skipped = Rc<RefCell<Vec<ParseAtomError>>>;
+
 #[derive(Debug, Clone)]
 pub(crate) struct AtomParser<'de> {
     pub(crate) input: &'de str,
     pub(crate) span: Range<usize>,
+    pub(crate) options: Options,
+    /// Reads a placeholder in place of an atom that can't be read, after putting its error
+    /// here, so that the rest of the input is still checked
+    pub(crate) skipped: Option<Skipped>,
 }
 
 #[derive(Debug, Error, PartialEq, Eq, Clone)]
@@ -38,18 +54,34 @@ pub(crate) struct AtomParser<'de> {
 )]
 pub enum ParseAtomError {
     #[error("Expected an integer, found something else")]
-    ExpectedInteger(#[from] ParseIntError),
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::atom::integer)))]
+    ExpectedInteger {
+        source: ParseIntError,
+        #[cfg_attr(feature = "miette", label("{source}"))]
+        span: Range<usize>,
+    },
     #[error("Expected an float, found something else")]
-    ExpectedFloat(#[from] ParseFloatError),
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::atom::float)))]
+    ExpectedFloat {
+        source: ParseFloatError,
+        #[cfg_attr(feature = "miette", label("{source}"))]
+        span: Range<usize>,
+    },
     #[error("Expected a boolean, found something else")]
     #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::atom::bool)))]
     ExpectedBool(#[cfg_attr(feature = "miette", label)] Range<usize>),
+    #[error("Expected a single character, found something else")]
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::atom::char)))]
+    ExpectedChar(#[cfg_attr(feature = "miette", label)] Range<usize>),
+    #[error("Expected bytes in hex or base64, found something else")]
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::atom::bytes)))]
+    ExpectedBytes(#[cfg_attr(feature = "miette", label)] Range<usize>),
     #[error("Expected the start of a tuple, found something else")]
     #[cfg_attr(
         feature = "miette",
         diagnostic(code(divatree::parser::atom::tuple::start))
     )]
-    ExpectedTupleStart(Range<usize>),
+    ExpectedTupleStart(#[cfg_attr(feature = "miette", label)] Range<usize>),
     #[error("Expected a non empty tuple, found an empty tuple")]
     #[cfg_attr(
         feature = "miette",
@@ -67,25 +99,134 @@ pub enum ParseAtomError {
         #[cfg_attr(feature = "miette", label("Tuple should end here"))]
         expected_end: Range<usize>,
     },
-    #[error("A custom error has occured; See tracing logs.")]
-    Custom,
+    #[error("A {what} can't be read from a single atom")]
+    #[cfg_attr(
+        feature = "miette",
+        diagnostic(code(divatree::parser::atom::unsupported))
+    )]
+    Unsupported {
+        what: &'static str,
+        #[cfg_attr(feature = "miette", label)]
+        span: Range<usize>,
+    },
+    #[error("{message}")]
+    Custom {
+        message: String,
+        #[cfg_attr(feature = "miette", label)]
+        span: Range<usize>,
+    },
+}
+
+impl ParseAtomError {
+    /// The bytes the error points at.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Self::ExpectedInteger { span, .. }
+            | Self::ExpectedFloat { span, .. }
+            | Self::ExpectedBool(span)
+            | Self::ExpectedChar(span)
+            | Self::ExpectedBytes(span)
+            | Self::ExpectedTupleStart(span)
+            | Self::ExpectedNonEmptyTuple(span)
+            | Self::ExpectedTupleEnd {
+                expected_end: span, ..
+            }
+            | Self::Unsupported { span, .. }
+            | Self::Custom { span, .. } => span.clone(),
+        }
+    }
+
+    /// Gives `span` to errors that were made without knowing where they are.
+    pub(crate) fn or_span(self, span: Range<usize>) -> Self {
+        match self {
+            Self::Custom { message, span: old } if old.is_empty() => Self::Custom { message, span },
+            err => err,
+        }
+    }
 }
 
 impl serde::de::Error for ParseAtomError {
     fn custom<T: Display>(msg: T) -> Self {
-        #[cfg(feature="tracing")]
-        tracing::error!(err=tracing::field::display(msg));
-        Self::Custom
+        Self::Custom {
+            message: msg.to_string(),
+            span: Range::default(),
+        }
     }
 }
 
 impl<'de> AtomParser<'de> {
-    fn convert_value<T>(&self) -> Result<T, ParseAtomError>
+    /// Reads an integer in the radix of its prefix, like `0x14012316`.
+    fn int<T>(&self) -> Result<T, ParseAtomError>
     where
-        T: FromStr,
-        ParseAtomError: From<T::Err>,
+        T: Integer,
     {
-        self.input.parse().map_err(ParseAtomError::from)
+        radix::parse(self.input)
+            .map(|(x, _)| x)
+            .map_err(|source| ParseAtomError::ExpectedInteger {
+                source,
+                span: self.span.clone(),
+            })
+    }
+
+    fn float<T>(&self) -> Result<T, ParseAtomError>
+    where
+        T: FromStr<Err = ParseFloatError>,
+    {
+        self.input
+            .parse()
+            .map_err(|source| ParseAtomError::ExpectedFloat {
+                source,
+                span: self.span.clone(),
+            })
+    }
+
+    /// Puts `result` in [`AtomParser::skipped`] when it's an error, and reads the default value
+    /// in its place.
+    fn or_skip<T: Default>(&self, result: Result<T, ParseAtomError>) -> Result<T, ParseAtomError> {
+        match (result, &self.skipped) {
+            (Err(err), Some(skipped)) => {
+                skipped.borrow_mut().push(err);
+                Ok(T::default())
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Decodes the atom as hex, with an optional `0x` prefix, or as padded base64 otherwise.
+    fn bytes(&self) -> Result<Vec<u8>, ParseAtomError> {
+        let input = self.input;
+        let hex = input
+            .strip_prefix("0x")
+            .or_else(|| input.strip_prefix("0X"))
+            .unwrap_or(input);
+        let is_hex = hex.len() % 2 == 0 && hex.bytes().all(|x| x.is_ascii_hexdigit());
+        let bytes = if is_hex {
+            decode_hex(hex)
+        } else {
+            decode_base64(input)
+        };
+        bytes.ok_or_else(|| ParseAtomError::ExpectedBytes(self.span.clone()))
+    }
+
+    fn unsupported(&self, what: &'static str) -> ParseAtomError {
+        ParseAtomError::Unsupported {
+            what,
+            span: self.span.clone(),
+        }
+    }
+
+    /// The bytes `s` covers in the input, or the whole atom if `s` isn't a part of it.
+    fn span_of(&self, s: &str) -> Range<usize> {
+        let offset = (s.as_ptr() as usize)
+            .checked_sub(self.input.as_ptr() as usize)
+            .filter(|offset| offset + s.len() <= self.input.len());
+        match offset {
+            Some(offset) => {
+                let start = self.span.start + offset;
+                start..start + s.len()
+            }
+            None => self.span.clone(),
+        }
     }
 }
 
@@ -97,15 +238,30 @@ impl<'de> Deserializer<'de> for AtomParser<'de> {
     where
         V: Visitor<'de>,
     {
+        let inference = self.options.inference();
         let len = self.input.split(',').count();
-        if self.input.starts_with('(') && self.input.ends_with(')') && len > 0 {
+        let digits = self.input.strip_prefix(['+', '-']).unwrap_or(self.input);
+        // `007` is more likely an ID than a number
+        let leading_zero = digits.len() > 1
+            && digits.starts_with('0')
+            && digits[1..].starts_with(|x: char| x.is_ascii_digit());
+        let number = !(leading_zero && inference.leading_zeros())
+            && (inference.radix() || Radix::of(self.input) == Radix::Decimal);
+        if inference.tuples()
+            && self.input.starts_with('(')
+            && self.input.ends_with(')')
+            && len > 0
+        {
             self.deserialize_tuple(len, visitor)
-        } else if self.convert_value::<i64>().is_ok() {
+        } else if number && inference.integers() && self.int::<i64>().is_ok() {
             self.deserialize_i64(visitor)
-        } else if self.convert_value::<f64>().is_ok() {
+        } else if number && inference.integers() && self.int::<u64>().is_ok() {
+            self.deserialize_u64(visitor)
+        } else if number && inference.floats() && self.float::<f64>().is_ok() {
             self.deserialize_f64(visitor)
-        } else if self.input.eq_ignore_ascii_case("true")
-            || self.input.eq_ignore_ascii_case("false")
+        } else if inference.bools()
+            && (self.input.eq_ignore_ascii_case("true")
+                || self.input.eq_ignore_ascii_case("false"))
         {
             self.deserialize_bool(visitor)
         } else {
@@ -117,91 +273,95 @@ impl<'de> Deserializer<'de> for AtomParser<'de> {
     where
         V: Visitor<'de>,
     {
-        let val = self.input;
-        if val.eq_ignore_ascii_case("true") {
-            visitor.visit_bool(true)
-        } else if val.eq_ignore_ascii_case("false") {
-            visitor.visit_bool(false)
-        } else {
-            Err(ParseAtomError::ExpectedBool(self.span.clone()))
-        }
+        let v = self
+            .options
+            .bools()
+            .iter()
+            .find_map(|x| x.parse(self.input))
+            .ok_or_else(|| ParseAtomError::ExpectedBool(self.span.clone()));
+        visitor.visit_bool(self.or_skip(v)?)
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i8(self.convert_value()?)
+        visitor.visit_i8(self.or_skip(self.int())?)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i16(self.convert_value()?)
+        visitor.visit_i16(self.or_skip(self.int())?)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i32(self.convert_value()?)
+        visitor.visit_i32(self.or_skip(self.int())?)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_i64(self.convert_value()?)
+        visitor.visit_i64(self.or_skip(self.int())?)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u8(self.convert_value()?)
+        visitor.visit_u8(self.or_skip(self.int())?)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u16(self.convert_value()?)
+        visitor.visit_u16(self.or_skip(self.int())?)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u32(self.convert_value()?)
+        visitor.visit_u32(self.or_skip(self.int())?)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_u64(self.convert_value()?)
+        visitor.visit_u64(self.or_skip(self.int())?)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f32(self.convert_value()?)
+        visitor.visit_f32(self.or_skip(self.float())?)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f64(self.convert_value()?)
+        visitor.visit_f64(self.or_skip(self.float())?)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        let mut chars = self.input.chars();
+        let c = match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(ParseAtomError::ExpectedChar(self.span.clone())),
+        };
+        visitor.visit_char(self.or_skip(c)?)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -222,14 +382,14 @@ impl<'de> Deserializer<'de> for AtomParser<'de> {
     where
         V: Visitor<'de>,
     {
-        todo!()
+        self.deserialize_byte_buf(visitor)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_byte_buf(self.or_skip(self.bytes())?)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -268,42 +428,33 @@ impl<'de> Deserializer<'de> for AtomParser<'de> {
         visitor.visit_newtype_struct(self)
     }
 
+    /// Reads a comma separated list, like `1, 2, 3`, with or without the parentheses of a
+    /// tuple around it.
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        let input = self.input;
+        let list = input
+            .strip_prefix('(')
+            .and_then(|x| x.strip_suffix(')'))
+            .unwrap_or(input);
+        visitor.visit_seq(TupleParser {
+            atom: &self,
+            members: list.split(','),
+        })
     }
 
     fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        struct TupleParser<'a>(Split<'a, char>);
-
-        impl<'a, 'de> SeqAccess<'de> for TupleParser<'de> {
-            type Error = ParseAtomError;
-
-            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
-            where
-                T: serde::de::DeserializeSeed<'de>,
-            {
-                self.0
-                    .next()
-                    .map(|x| {
-                        seed.deserialize(AtomParser {
-                            input: x.trim(),
-                            // TODO: implement this
-                            span: Default::default(),
-                        })
-                    })
-                    .transpose()
-            }
-        }
-
         if let Some(prefix) = self.input.strip_prefix('(') {
             if let Some(vals) = prefix.strip_suffix(')') {
-                visitor.visit_seq(TupleParser(vals.split(',')))
+                visitor.visit_seq(TupleParser {
+                    atom: &self,
+                    members: vals.split(','),
+                })
             } else {
                 let mut start = self.span.clone();
                 start.end = start.start;
@@ -335,25 +486,26 @@ impl<'de> Deserializer<'de> for AtomParser<'de> {
         self.deserialize_tuple(len, visitor)
     }
 
-    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        Err(self.unsupported("map"))
     }
 
     fn deserialize_struct<V>(
         self,
         name: &'static str,
         fields: &'static [&'static str],
-        visitor: V,
+        _visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        todo!()
+        Err(self.unsupported("struct"))
     }
 
+    /// Reads a unit variant from its name, like `PV_LV_01_0`.
     fn deserialize_enum<V>(
         self,
         name: &'static str,
@@ -363,7 +515,7 @@ impl<'de> Deserializer<'de> for AtomParser<'de> {
     where
         V: Visitor<'de>,
     {
-        todo!()
+        visitor.visit_enum(self)
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -381,6 +533,119 @@ impl<'de> Deserializer<'de> for AtomParser<'de> {
     }
 }
 
+/// The members of a tuple or a list, each with its own span.
+struct TupleParser<'a, 'de> {
+    atom: &'a AtomParser<'de>,
+    members: Split<'de, char>,
+}
+
+impl<'a, 'de> SeqAccess<'de> for TupleParser<'a, 'de> {
+    type Error = ParseAtomError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.members
+            .next()
+            .map(|x| x.trim())
+            // A trailing comma doesn't start another member
+            .filter(|x| !x.is_empty() || self.members.clone().next().is_some())
+            .map(|input| {
+                let span = self.atom.span_of(input);
+                let atom = AtomParser {
+                    input,
+                    span: span.clone(),
+                    options: self.atom.options,
+                    skipped: self.atom.skipped.clone(),
+                };
+                seed.deserialize(atom)
+                    .map_err(|e| e.or_span(span))
+            })
+            .transpose()
+    }
+}
+
+impl<'de> EnumAccess<'de> for AtomParser<'de> {
+    type Error = ParseAtomError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed
+            .deserialize(self.input.into_deserializer())
+            .map_err(|e: ParseAtomError| e.or_span(self.span.clone()))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for AtomParser<'de> {
+    type Error = ParseAtomError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        Err(self.unsupported("newtype variant"))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(self.unsupported("tuple variant"))
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(self.unsupported("struct variant"))
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    if s.len() % 4 != 0 {
+        return None;
+    }
+    let data = s.trim_end_matches('=');
+    if s.len() - data.len() > 2 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buf = 0u32;
+    let mut bits = 0;
+    for c in data.bytes() {
+        let val = ALPHABET.iter().position(|x| *x == c)? as u32;
+        buf = buf << 6 | val;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+            buf &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -393,6 +658,18 @@ mod tests {
             from_str::<bool>("foo"),
             Err(ParseAtomError::ExpectedBool(0..3))
         );
+        assert_eq!(from_str("1"), Ok(true));
+        assert_eq!(from_str("0"), Ok(false));
+        assert_eq!(from_str("On"), Ok(true));
+        assert_eq!(from_str("no"), Ok(false));
+        assert_eq!(from_str::<bool>("2"), Err(ParseAtomError::ExpectedBool(0..1)));
+        let atom = AtomParser {
+            input: "1",
+            span: 4..5,
+            options: Options::default().with_bools(&[crate::BoolStyle::TrueFalse]),
+            skipped: None,
+        };
+        assert_eq!(bool::deserialize(atom), Err(ParseAtomError::ExpectedBool(4..5)));
     }
 
     #[test]
@@ -402,9 +679,94 @@ mod tests {
         assert_eq!(from_str("255"), Ok(255u8));
         assert_eq!(from_str("+256"), Ok(256u16));
         match from_str::<u8>("foo") {
-            Err(ParseAtomError::ExpectedInteger(_)) => {}
+            Err(ParseAtomError::ExpectedInteger { span, .. }) => assert_eq!(span, 0..3),
             _ => unreachable!(),
         }
+        assert_eq!(from_str("0x14012316"), Ok(0x14012316u32));
+        assert_eq!(from_str("-0b1000"), Ok(-8i8));
+        assert_eq!(from_str("0o777"), Ok(0o777u16));
+        assert!(from_str::<u8>("0x1ff").is_err());
+    }
+
+    #[test]
+    fn read_any_int() {
+        use crate::Value;
+
+        #[derive(Debug, PartialEq, serde_derive::Deserialize)]
+        #[serde(untagged)]
+        enum Any {
+            Int(i64),
+            Uint(u64),
+            Float(f64),
+            Str(String),
+        }
+        let any = |input| {
+            let atom = AtomParser {
+                input,
+                span: 0..input.len(),
+                options: Options::default().with_inference(Inference::default().with_radix(true)),
+                skipped: None,
+            };
+            Any::deserialize(atom).unwrap()
+        };
+        assert_eq!(any("0x14012316"), Any::Int(0x14012316));
+        assert_eq!(any("0xffffffffffffffff"), Any::Uint(u64::MAX));
+        assert_eq!(any("0x"), Any::Str("0x".into()));
+        assert_eq!(from_str("0x14012316"), Ok(Any::Str("0x14012316".into())));
+        assert_eq!(from_str("-0b1000"), Ok(Any::Str("-0b1000".into())));
+        assert_eq!(
+            crate::from_str::<Value>("a=0x10\n").unwrap()["a"].as_str(),
+            Some("0x10")
+        );
+    }
+
+    #[test]
+    fn read_any_inference() {
+        #[derive(Debug, PartialEq, serde_derive::Deserialize)]
+        #[serde(untagged)]
+        enum Any {
+            Bool(bool),
+            Int(i64),
+            Float(f64),
+            Str(String),
+            Tuple(Vec<Any>),
+        }
+        let any = |input, inference| {
+            let atom = AtomParser {
+                input,
+                span: 0..input.len(),
+                options: Options::default().with_inference(inference),
+                skipped: None,
+            };
+            Any::deserialize(atom).unwrap()
+        };
+        let keep_zeros = Inference::default().with_leading_zeros(true);
+        assert_eq!(any("007", Inference::default()), Any::Int(7));
+        assert_eq!(any("007", keep_zeros), Any::Str("007".into()));
+        assert_eq!(any("-007", keep_zeros), Any::Str("-007".into()));
+        assert_eq!(any("00.5", keep_zeros), Any::Str("00.5".into()));
+        assert_eq!(any("0", keep_zeros), Any::Int(0));
+        assert_eq!(any("0.5", keep_zeros), Any::Float(0.5));
+        assert_eq!(any("0x10", keep_zeros), Any::Str("0x10".into()));
+        assert_eq!(any("0x10", keep_zeros.with_radix(true)), Any::Int(16));
+        let no_floats = Inference::default().with_floats(false);
+        assert_eq!(any("1.00", no_floats), Any::Str("1.00".into()));
+        assert_eq!(any("1", no_floats), Any::Int(1));
+        for input in &["1", "1.5", "true", "(1, 2)"] {
+            assert_eq!(any(input, Inference::strings()), Any::Str(input.to_string()));
+        }
+        assert_eq!(
+            any("(1, a)", Inference::default()),
+            Any::Tuple(vec![Any::Int(1), Any::Str("a".into())])
+        );
+        assert_eq!(
+            any("(1, a)", Inference::default().with_tuples(false)),
+            Any::Str("(1, a)".into())
+        );
+        assert_eq!(
+            any("TRUE", Inference::default().with_bools(false)),
+            Any::Str("TRUE".into())
+        );
     }
 
     #[test]
@@ -416,7 +778,7 @@ mod tests {
         assert_eq!(from_str("-1.234"), Ok(-1.234f32));
         assert_eq!(from_str("6.02e23"), Ok(6.02e23f64));
         match from_str::<f32>("foo") {
-            Err(ParseAtomError::ExpectedFloat(_)) => {}
+            Err(ParseAtomError::ExpectedFloat { span, .. }) => assert_eq!(span, 0..3),
             _ => unreachable!(),
         }
     }
@@ -437,5 +799,113 @@ mod tests {
             Err(ParseAtomError::ExpectedTupleEnd { .. }) => {}
             e => unreachable!("{:?}", e),
         }
+        let input = "(1, foo, 3)";
+        match from_str::<(u8, u8, u8)>(input) {
+            Err(ParseAtomError::ExpectedInteger { span, .. }) => assert_eq!(&input[span], "foo"),
+            e => unreachable!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn span_of() {
+        let atom = AtomParser {
+            input: "(1, 2)",
+            span: 4..10,
+            options: Options::default(),
+            skipped: None,
+        };
+        assert_eq!(atom.span_of(&atom.input[4..5]), 8..9);
+        assert_eq!(atom.span_of(&String::from("2")), 4..10);
+    }
+
+    #[test]
+    fn read_char() {
+        assert_eq!(from_str("a"), Ok('a'));
+        assert_eq!(from_str("ミ"), Ok('ミ'));
+        assert_eq!(from_str::<char>("ab"), Err(ParseAtomError::ExpectedChar(0..2)));
+        assert_eq!(from_str::<char>(""), Err(ParseAtomError::ExpectedChar(0..0)));
+    }
+
+    #[test]
+    fn read_bytes() {
+        struct Bytes(Vec<u8>);
+        impl<'de> Deserialize<'de> for Bytes {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct BytesVisitor;
+                impl<'de> Visitor<'de> for BytesVisitor {
+                    type Value = Bytes;
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("bytes")
+                    }
+                    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Bytes, E> {
+                        Ok(Bytes(v))
+                    }
+                }
+                deserializer.deserialize_byte_buf(BytesVisitor)
+            }
+        }
+        let bytes = |s| from_str::<Bytes>(s).map(|x| x.0);
+        assert_eq!(bytes("dead0B"), Ok(vec![0xde, 0xad, 0x0b]));
+        assert_eq!(bytes("0x0102"), Ok(vec![1, 2]));
+        assert_eq!(bytes("SGVsbG8="), Ok(b"Hello".to_vec()));
+        assert_eq!(bytes("SGk="), Ok(b"Hi".to_vec()));
+        assert_eq!(bytes(""), Ok(vec![]));
+        assert_eq!(bytes("SGk"), Err(ParseAtomError::ExpectedBytes(0..3)));
+        assert_eq!(bytes("S=Gk"), Err(ParseAtomError::ExpectedBytes(0..4)));
+    }
+
+    #[test]
+    fn read_seq() {
+        assert_eq!(from_str("1, 2,3"), Ok(vec![1u8, 2, 3]));
+        assert_eq!(from_str("(1, 2, 3, )"), Ok(vec![1u8, 2, 3]));
+        assert_eq!(from_str("()"), Ok(Vec::<u8>::new()));
+        assert_eq!(from_str(""), Ok(Vec::<u8>::new()));
+        let input = "1, 2, x";
+        match from_str::<Vec<u8>>(input) {
+            Err(ParseAtomError::ExpectedInteger { span, .. }) => assert_eq!(&input[span], "x"),
+            e => unreachable!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn read_enum() {
+        #[derive(Debug, PartialEq, serde_derive::Deserialize)]
+        enum Level {
+            #[serde(rename = "PV_LV_01_0")]
+            Easy,
+            #[serde(rename = "PV_LV_10_0")]
+            Hard,
+            Custom(u8),
+        }
+        assert_eq!(from_str("PV_LV_10_0"), Ok(Level::Hard));
+        assert_eq!(from_str("PV_LV_01_0"), Ok(Level::Easy));
+        match from_str::<Level>("PV_LV_11_0") {
+            Err(ParseAtomError::Custom { span, .. }) => assert_eq!(span, 0..10),
+            e => unreachable!("{:?}", e),
+        }
+        assert_eq!(
+            from_str::<Level>("Custom"),
+            Err(ParseAtomError::Unsupported {
+                what: "newtype variant",
+                span: 0..6
+            })
+        );
+    }
+
+    #[test]
+    fn read_unsupported() {
+        #[derive(Debug, serde_derive::Deserialize)]
+        struct Foo {}
+        assert_eq!(
+            from_str::<std::collections::HashMap<String, u8>>("foo"),
+            Err(ParseAtomError::Unsupported {
+                what: "map",
+                span: 0..3
+            })
+        );
+        match from_str::<Foo>("foo") {
+            Err(ParseAtomError::Unsupported { what, .. }) => assert_eq!(what, "struct"),
+            e => unreachable!("{:?}", e),
+        }
     }
 }
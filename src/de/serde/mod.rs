@@ -9,57 +9,169 @@ use std::num::{ParseFloatError, ParseIntError};
 use std::ops::Range;
 use std::str::FromStr;
 
-use super::{KeyValue, LexerChildren};
-use crate::error::DeserializerError;
-use crate::serde::atom::AtomParser;
+use super::{KeyValue, LexerChildren, Source};
+use crate::error::{DeserializerError, ErrorKind};
+use crate::serde::atom::{AtomParser, ParseAtomError, Skipped};
+use crate::value::{self, RawValueAccess};
 
 pub mod atom;
+mod options;
+pub(crate) mod read;
+mod recover;
+
+pub use self::options::{DuplicateKeys, Inference, LengthCheck, Options};
+
+/// Reads `T` from `reader`, such as a file or a pipe.
+///
+/// The whole input is read into a `String` first, as the parser borrows every line from it.
+pub fn from_reader<R, T>(reader: R) -> Result<T, DeserializerError>
+where
+    R: std::io::Read,
+    T: de::DeserializeOwned,
+{
+    Options::default().from_reader(reader)
+}
+
+/// Reads `T` from already split lines, which are joined into a single `String` first like
+/// [`from_reader`] does.
+pub fn from_lines<I, T>(lines: I) -> Result<T, DeserializerError>
+where
+    I: IntoIterator<Item = String>,
+    T: de::DeserializeOwned,
+{
+    Options::default().from_lines(lines)
+}
+
+pub fn from_slice<'a, T>(v: &'a [u8]) -> Result<T, DeserializerError>
+where
+    T: Deserialize<'a>,
+{
+    Options::default().from_slice(v)
+}
 
 pub fn from_str<'a, T>(s: &'a str) -> Result<T, DeserializerError>
 where
     T: Deserialize<'a>,
 {
-    let mut iter = s
-        .lines()
-        .filter(|x| !x.trim().is_empty())
-        .filter(|x| !x.starts_with('#'));
-    let start = s
-        .lines()
-        .take_while(|x| x.starts_with('#') || x.is_empty())
-        .map(|x| x.len() + 1)
-        .sum();
-    let mut lex = Parser::new(iter);
-    #[cfg(feature = "tracing")]
-    tracing::info!("Setting start to: {}", start);
-    lex.iter.byte_offset = start..start;
-    T::deserialize(&mut lex)
+    Options::default().from_str(s)
+}
+
+/// Reads `T` from `s`, which was read from the file `name`.
+///
+/// Errors own a copy of `s`, so they can show the offending line with the name of the file.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// let err = serde_divatree::from_str_named::<HashMap<String, u32>>("pv_db.txt", "foo=bar")
+///     .unwrap_err();
+/// assert_eq!(err.file_name(), Some("pv_db.txt"));
+/// assert!(err.to_string().starts_with("pv_db.txt:1:5: "));
+/// ```
+pub fn from_str_named<'a, T>(name: &str, s: &'a str) -> Result<T, DeserializerError>
+where
+    T: Deserialize<'a>,
+{
+    Options::default().from_str_named(name, s)
+}
+
+/// Reads as much of `T` from `s` as possible, along with every error found on the way.
+///
+/// The subtree of every error is left out, so a broken entry of a map is skipped instead of
+/// failing the whole file. `None` is returned when not even that was enough. The errors are in
+/// the order of the lines they are on.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// let input = "pv_001.bpm=120
+/// pv_002.bpm=fast
+/// pv_003.bpm=150
+/// pv_004.bpm=slow
+/// ";
+/// let (pvs, errors) =
+///     serde_divatree::from_str_recovering::<HashMap<String, HashMap<String, u32>>>(input);
+/// let pvs = pvs.unwrap();
+/// assert_eq!(pvs.len(), 2);
+/// assert_eq!(pvs["pv_003"]["bpm"], 150);
+/// assert_eq!(errors.len(), 2);
+/// assert_eq!(errors[1].path(), Some("pv_004.bpm"));
+/// ```
+pub fn from_str_recovering<'a, T>(s: &'a str) -> (Option<T>, Vec<DeserializerError>)
+where
+    T: Deserialize<'a>,
+{
+    Options::default().from_str_recovering(s)
 }
 
 pub(crate) struct Parser<'de, I: Iterator> {
     iter: LexerChildren<'de, Peekable<I>>,
     deser_any_col: bool,
+    options: Options,
+    /// Where the lines come from, for the spans of errors
+    source: Source<'de>,
+    /// Where atoms that can't be read go while recovering, see [`AtomParser::skipped`]
+    skipped: Option<Skipped>,
 }
 
 impl<'de, I: Iterator<Item = &'de str>> Parser<'de, I> {
     pub(crate) fn new(iter: I) -> Self {
+        Self::with_options(iter, Options::default())
+    }
+    pub(crate) fn with_options(iter: I, options: Options) -> Self {
         let iter = LexerChildren::new(iter.peekable());
         Self {
             iter,
             deser_any_col: false,
+            options,
+            source: Source::Unknown,
+            skipped: None,
+        }
+    }
+    pub(crate) fn with_source(mut self, source: Source<'de>) -> Self {
+        self.source = source;
+        self
+    }
+    pub(crate) fn with_skipped(mut self, skipped: Option<Skipped>) -> Self {
+        self.skipped = skipped;
+        self
+    }
+    /// The bytes `s` covers in the input, where `s` is a part of the current line.
+    fn span(&self, s: &str) -> Range<usize> {
+        if let Some(start) = self.source.offset(s) {
+            return start..start + s.len();
         }
+        // Without a source, count from the lines read so far
+        let prefix = self.iter.prefix.map_or(0, str::len);
+        let mut start = self.iter.byte_offset.start.saturating_sub(prefix);
+        if let Some(line) = self.iter.cache {
+            start += Source::Str(line).offset(s).unwrap_or_default();
+        }
+        start..start + s.len()
     }
-    fn key_value(&mut self) -> Option<KeyValue<'de>> {
-        self.iter.next().and_then(KeyValue::new)
+    /// The span of the last line read, for errors about a line that's missing.
+    pub(super) fn last_span(&self) -> Range<usize> {
+        self.iter
+            .cache
+            .map_or_else(Range::default, |line| self.span(line))
     }
     fn value(&mut self) -> Result<(&'de str, Range<usize>), DeserializerError> {
-        let kv = self
-            .key_value()
-            .ok_or(DeserializerError::ExpectedValueNode)?;
+        let line = match self.iter.next() {
+            Some(line) => line,
+            None => {
+                let span = self.last_span();
+                return Err(ErrorKind::ExpectedValueNode { span }.into());
+            }
+        };
+        let kv = KeyValue::new(line).ok_or_else(|| ErrorKind::ExpectedKeyValuePair {
+            span: self.span(line),
+        })?;
         let val = kv.path().next().unwrap_or(kv.value);
-        // SAFETY: `val` comes from `kv.orig`
-        let value_start =
-            val.as_ptr() as usize - kv.orig.as_ptr() as usize + self.iter.byte_offset.start;
-        let range = value_start..value_start + val.len();
+        let range = self.span(val);
         #[cfg(feature = "tracing")]
         tracing::trace!(
             full = kv.orig,
@@ -71,16 +183,54 @@ impl<'de, I: Iterator<Item = &'de str>> Parser<'de, I> {
     }
     fn atom(&mut self) -> Result<AtomParser<'de>, DeserializerError> {
         let (input, span) = self.value()?;
-        Ok(AtomParser { input, span })
+        Ok(AtomParser {
+            input,
+            span,
+            options: self.options,
+            skipped: self.skipped.clone(),
+        })
+    }
+    /// Consumes every line of the current node, with the node's own prefix stripped.
+    fn group(&mut self) -> Result<String, DeserializerError> {
+        let mut lines = Vec::new();
+        while !self.iter.is_finished() {
+            let line = match self.iter.next() {
+                Some(line) => line,
+                None => break,
+            };
+            lines.push(line);
+            let kv = KeyValue::new(line).ok_or_else(|| ErrorKind::ExpectedKeyValuePair {
+                span: self.span(line),
+            })?;
+            // The prefix of an atom doesn't end with a delimiter, so it would also match its
+            // siblings sharing the same start. e.g. `foo` and `foobar`
+            if kv.key.is_empty() {
+                break;
+            }
+        }
+        Ok(lines.join("\n"))
     }
 }
 
 impl<'de, I: Iterator<Item = &'de str>> Parser<'de, I> {
     fn peek_key_value(&mut self) -> Result<KeyValue<'de>, DeserializerError> {
-        self.iter
-            .peek()
-            .and_then(KeyValue::new)
-            .ok_or(DeserializerError::ExpectedKeyValuePair)
+        let line = self.iter.peek();
+        line.and_then(KeyValue::new)
+            .ok_or_else(|| {
+                ErrorKind::ExpectedKeyValuePair {
+                    span: line.map_or_else(|| self.last_span(), |x| self.span(x)),
+                }
+                .into()
+            })
+    }
+    /// Reads the current atom with `f`, pointing errors without a span at the atom.
+    fn with_atom<T>(
+        &mut self,
+        f: impl FnOnce(AtomParser<'de>) -> Result<T, ParseAtomError>,
+    ) -> Result<T, DeserializerError> {
+        let atom = self.atom()?;
+        let span = atom.span.clone();
+        f(atom).map_err(|e| e.or_span(span).into())
     }
 }
 
@@ -105,7 +255,7 @@ where
                 self.deserialize_map(visitor)
             }
         } else {
-            self.atom()?.deserialize_any(visitor).map_err(Into::into)
+            self.with_atom(|x| x.deserialize_any(visitor))
         }
     }
 
@@ -113,28 +263,28 @@ where
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_bool(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_bool(visitor))
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_i8(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_i8(visitor))
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_i16(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_i16(visitor))
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_i32(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_i32(visitor))
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, visitor)))]
@@ -142,86 +292,84 @@ where
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_i64(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_i64(visitor))
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_u8(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_u8(visitor))
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_u16(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_u16(visitor))
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_u32(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_u32(visitor))
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_u64(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_u64(visitor))
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_f32(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_f32(visitor))
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_f64(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_f64(visitor))
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_char(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_char(visitor))
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_str(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_str(visitor))
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_string(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_string(visitor))
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?.deserialize_bytes(visitor).map_err(Into::into)
+        self.with_atom(|x| x.deserialize_bytes(visitor))
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        self.atom()?
-            .deserialize_byte_buf(visitor)
-            .map_err(Into::into)
+        self.with_atom(|x| x.deserialize_byte_buf(visitor))
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -257,7 +405,11 @@ where
     where
         V: Visitor<'de>,
     {
-        visitor.visit_newtype_struct(self)
+        if name == value::TOKEN {
+            visitor.visit_map(RawValueAccess::new(self.group()?))
+        } else {
+            visitor.visit_newtype_struct(self)
+        }
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, visitor)))]
@@ -272,9 +424,7 @@ where
     where
         V: Visitor<'de>,
     {
-        self.atom()?
-            .deserialize_tuple(len, visitor)
-            .map_err(Into::into)
+        self.with_atom(|x| x.deserialize_tuple(len, visitor))
     }
 
     fn deserialize_tuple_struct<V>(
@@ -324,9 +474,7 @@ where
     where
         V: Visitor<'de>,
     {
-        self.atom()?
-            .deserialize_identifier(visitor)
-            .map_err(Into::into)
+        self.with_atom(|x| x.deserialize_identifier(visitor))
     }
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -376,7 +524,7 @@ impl<'de, I: Iterator<Item = &'de str> + 'de> MapAccess<'de> for Parser<'de, I>
     }
 }
 
-const SEQ_ENDER: &'static [&'static str] = &["length", "num"];
+pub(crate) const SEQ_ENDER: &'static [&'static str] = &["length", "num"];
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
@@ -415,6 +563,38 @@ impl<'a, 'de, I: Iterator<Item = &'de str>> SeqParser<'a, 'de, I> {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<'a, 'de, I: Iterator<Item = &'de str>> SeqParser<'a, 'de, I> {
+    /// Compares the declared length with the indices read so far.
+    fn check_length(&self, span: Range<usize>) -> Result<(), DeserializerError> {
+        let length = match self.read_length {
+            Some(length) => length,
+            None => return Ok(()),
+        };
+        let extra: Vec<_> = self.read_indices.range(length.max(0)..).copied().collect();
+        let found = self.read_indices.len();
+        // Indices are unique, so every one below `length` is there when there are enough
+        if extra.is_empty() && found as i64 == length.max(0) {
+            return Ok(());
+        }
+        let err = ErrorKind::LengthMismatch {
+            length,
+            found,
+            extra,
+            span,
+        };
+        let err = DeserializerError::from(err).locate(self.de.source);
+        match self.de.options.length_check() {
+            LengthCheck::Strict => Err(err),
+            LengthCheck::Lenient => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = tracing::field::debug(&err), "{}", err);
+                Ok(())
+            }
+        }
+    }
+}
+
 impl<'a, 'de, I: Iterator<Item = &'de str> + 'de> SeqAccess<'de> for SeqParser<'a, 'de, I> {
     type Error = DeserializerError;
 
@@ -440,7 +620,7 @@ impl<'a, 'de, I: Iterator<Item = &'de str> + 'de> SeqAccess<'de> for SeqParser<'
                 // 0-9 < a-z
                 Ok(None)
             } else {
-                Err(DeserializerError::ExpectedSequenece { unexpected: span })
+                Err(ErrorKind::ExpectedSequenece { unexpected: span }.into())
             }
         }
         #[cfg(feature = "alloc")]
@@ -467,17 +647,23 @@ impl<'a, 'de, I: Iterator<Item = &'de str> + 'de> SeqAccess<'de> for SeqParser<'
                 );
 
                 if ident.chars().all(|x| x.is_ascii_digit()) {
-                    let ident = ident.parse::<i64>().unwrap();
+                    let ident = ident
+                        .parse::<i64>()
+                        .map_err(|_| ErrorKind::IndexOverflow { span })?;
                     self.read_indices.insert(ident);
                 } else if SEQ_ENDER.iter().any(|x| ident.eq_ignore_ascii_case(x)) {
                     self.de.iter.increment_prefix_level();
                     let marker = std::marker::PhantomData::<i64>;
+                    // A broken length is ignored, so it isn't skipped while recovering either
+                    let skipped = self.de.skipped.take();
                     self.read_length = marker.deserialize(&mut *self.de).ok();
+                    self.de.skipped = skipped;
                     self.de.iter.decrement_prefix_level();
+                    self.check_length(span)?;
                     break;
                 } else {
                     tracing::error!(ident=ident, "Got something unexpected.");
-                    return Err(DeserializerError::ExpectedSequenece { unexpected: span });
+                    return Err(ErrorKind::ExpectedSequenece { unexpected: span }.into());
                 }
             }
 
@@ -492,7 +678,10 @@ impl<'a, 'de, I: Iterator<Item = &'de str> + 'de> SeqAccess<'de> for SeqParser<'
             }
 
             // TODO: get rid of this clone
-            let mut lookup = Parser::new(self.read_lines.clone().into_iter());
+            let mut lookup =
+                Parser::with_options(self.read_lines.clone().into_iter(), self.de.options)
+                    .with_source(self.de.source)
+                    .with_skipped(self.de.skipped.clone());
             if !self.read_indices.contains(&self.index)
                 || self
                     .read_length
@@ -514,8 +703,7 @@ impl<'a, 'de, I: Iterator<Item = &'de str> + 'de> SeqAccess<'de> for SeqParser<'
                     } else if SEQ_ENDER.iter().any(|x| ident.eq_ignore_ascii_case(x)) {
                         break;
                     } else {
-                        // HACK: get the actual span from the outer parser
-                        return Err(DeserializerError::ExpectedSequenece { unexpected: span });
+                        return Err(ErrorKind::ExpectedSequenece { unexpected: span }.into());
                     }
                 }
                 Ok(None)
@@ -525,12 +713,14 @@ impl<'a, 'de, I: Iterator<Item = &'de str> + 'de> SeqAccess<'de> for SeqParser<'
                     if lookup.iter.is_finished() {
                         break;
                     }
-                    let (ident, _span) = lookup.value()?;
+                    let (ident, span) = lookup.value()?;
                     #[cfg(feature = "tracing")]
                     tracing::debug!(ident= ident,  "Reading value.");
 
                     if ident.chars().all(|x| x.is_ascii_digit()) {
-                        let ident = ident.parse::<i64>().unwrap();
+                        let ident = ident
+                            .parse::<i64>()
+                            .map_err(|_| ErrorKind::IndexOverflow { span })?;
                         if ident == self.index {
                             lookup.iter.increment_prefix_level();
                             value = Some(seed.deserialize(&mut lookup));
@@ -554,6 +744,8 @@ impl<'a, 'de, I: Iterator<Item = &'de str> + 'de> EnumAccess<'de> for &'a mut Pa
 
     type Variant = Self;
 
+    /// Reads the variant from the next key, like `level.Custom=5`, or from the value when
+    /// there's no key left, like `level=PV_LV_01_0` for a unit variant.
     #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, seed)))]
     fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
     where
@@ -620,6 +812,7 @@ mod tests {
     use test_log::test;
 
     use super::*;
+    use crate::error::Location;
 
     #[test]
     fn read_map() {
@@ -636,6 +829,18 @@ quux = 4
         assert_eq!(from_str(input), Ok(expected));
     }
     #[test]
+    fn read_slice() {
+        let mut expected = HashMap::new();
+        expected.insert("foo", 1);
+        assert_eq!(from_slice(b"foo = 1"), Ok(expected));
+        match from_slice::<HashMap<&str, i32>>(b"foo = \xff")
+            .map_err(DeserializerError::into_kind)
+        {
+            Err(ErrorKind::Utf8(_)) => {}
+            e => unreachable!("{:?}", e),
+        }
+    }
+    #[test]
     fn read_nested_map() {
         let input = "foo.bar = 1
 foo.baz = 2
@@ -713,6 +918,414 @@ length=12";
         assert_eq!(data, (0..12).collect::<Vec<_>>());
     }
 
+    #[test]
+    fn read_seq_length_check() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Test {
+            lyric: Vec<String>,
+        }
+        let input = "lyric.0=La
+lyric.1=Li
+lyric.length=3
+";
+        let strict = Options::default().with_length_check(LengthCheck::Strict);
+        match strict.from_str::<Test>(input).map_err(DeserializerError::into_kind) {
+            Err(ErrorKind::LengthMismatch {
+                length,
+                found,
+                extra,
+                span,
+            }) => {
+                assert_eq!((length, found, extra), (3, 2, vec![]));
+                assert_eq!(&input[span], "length");
+            }
+            e => unreachable!("{:?}", e),
+        }
+        let lenient: Test = from_str(input).unwrap();
+        assert_eq!(lenient.lyric, vec!["La", "Li"]);
+
+        let past = "lyric.0=La\nlyric.2=Li\nlyric.length=2\n";
+        match strict.from_str::<Test>(past).map_err(DeserializerError::into_kind) {
+            Err(ErrorKind::LengthMismatch { extra, .. }) => assert_eq!(extra, vec![2]),
+            e => unreachable!("{:?}", e),
+        }
+        let input = "lyric.0=La\nlyric.1=Li\nlyric.length=2\n";
+        assert!(strict.from_str::<Test>(input).is_ok());
+    }
+
+    #[test]
+    fn read_seq_huge_index() {
+        let input = "99999999999999999999=1\nlength=1\n";
+        match from_str::<Vec<i32>>(input).map_err(DeserializerError::into_kind) {
+            Err(ErrorKind::IndexOverflow { span }) => {
+                assert_eq!(&input[span], "99999999999999999999")
+            }
+            e => unreachable!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn read_error_spans() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Test {
+            foo: u32,
+            list: Vec<(u8, u8)>,
+        }
+        let input = "# header
+foo=1
+
+# comment
+list.0=(1, 2)
+list.1=(3, x)
+list.length=2
+";
+        let span = |err: Result<Test, DeserializerError>| match err.unwrap_err().into_kind() {
+            ErrorKind::ParseAtomError(ParseAtomError::ExpectedInteger { span, .. }) => span,
+            e => unreachable!("{:?}", e),
+        };
+        assert_eq!(&input[span(from_str::<Test>(input))], "x");
+        assert_eq!(&input[span(from_reader::<_, Test>(input.as_bytes()))], "x");
+        let unordered = Options::default().with_unordered(true);
+        assert_eq!(&input[span(unordered.from_str::<Test>(input))], "x");
+
+        let input = "foo=1\nlist.0=(1, 2)\nlist.bar=3\nlist.length=2\n";
+        match from_str::<Test>(input).map_err(DeserializerError::into_kind) {
+            Err(ErrorKind::ExpectedSequenece { unexpected }) => {
+                assert_eq!(&input[unexpected], "bar")
+            }
+            e => unreachable!("{:?}", e),
+        }
+        let input = "foo=1\nlist\n";
+        match from_str::<Test>(input).map_err(DeserializerError::into_kind) {
+            Err(ErrorKind::ExpectedKeyValuePair { span }) => {
+                assert_eq!(&input[span], "list")
+            }
+            e => unreachable!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn read_unordered() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct PvDb {
+            pv_001: Pv,
+            pv_002: Pv,
+        }
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Pv {
+            song_name: String,
+            bpm: u32,
+            lyric: Vec<String>,
+        }
+        let input = "pv_001.song_name=Foo
+pv_001.lyric.0=La
+pv_002.song_name=Bar
+pv_002.lyric.length=0
+pv_001.bpm=120
+pv_001.lyric.length=2
+pv_002.bpm=180
+pv_001.lyric.1=Li
+";
+        let expected = PvDb {
+            pv_001: Pv {
+                song_name: "Foo".into(),
+                bpm: 120,
+                lyric: vec!["La".into(), "Li".into()],
+            },
+            pv_002: Pv {
+                song_name: "Bar".into(),
+                bpm: 180,
+                lyric: vec![],
+            },
+        };
+        assert!(from_str::<PvDb>(input).is_err());
+        let options = Options::default().with_unordered(true);
+        assert_eq!(options.from_str(input), Ok(expected));
+    }
+
+    #[test]
+    fn read_duplicate_keys() {
+        let input = "pv_001.bpm=120
+pv_001.song_name=Foo
+pv_001.bpm=150
+";
+        let err = from_str::<HashMap<String, HashMap<String, String>>>(input).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ErrorKind::DuplicateKey {
+                key: "pv_001.bpm".into(),
+                first: 0..10,
+                second: 36..46,
+            }
+        );
+        assert_eq!(
+            err.location(),
+            Some(Location {
+                line: 3,
+                column: 1,
+                byte: 36
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "3:1: The key `pv_001.bpm` is defined more than once"
+        );
+        let first: HashMap<String, HashMap<String, String>> = Options::default()
+            .with_duplicate_keys(DuplicateKeys::FirstWins)
+            .from_str(input)
+            .unwrap();
+        assert_eq!(first["pv_001"]["bpm"], "120");
+        let last: HashMap<String, HashMap<String, String>> = Options::default()
+            .with_duplicate_keys(DuplicateKeys::LastWins)
+            .from_str(input)
+            .unwrap();
+        assert_eq!(last["pv_001"]["bpm"], "150");
+        assert_eq!(last["pv_001"]["song_name"], "Foo");
+    }
+
+    #[test]
+    fn read_named() {
+        let input = "pv_001.bpm=120
+pv_001.song_name=Foo
+pv_001.bpm=150
+";
+        let err = from_str_named::<HashMap<String, HashMap<String, String>>>("pv_db.txt", input)
+            .unwrap_err();
+        assert_eq!(err.file_name(), Some("pv_db.txt"));
+        assert_eq!(err.source_text(), Some(input));
+        assert_eq!(
+            err.to_string(),
+            "pv_db.txt:3:1: The key `pv_001.bpm` is defined more than once"
+        );
+        let err = DeserializerError::from(ErrorKind::Custom("oops".into()))
+            .with_source_code("pv_db.txt", input);
+        assert_eq!(err.to_string(), "pv_db.txt: oops");
+    }
+
+    #[test]
+    fn read_bools() {
+        let input = "a=1
+b=off
+c=Yes
+";
+        let data: HashMap<String, bool> = from_str(input).unwrap();
+        assert_eq!((data["a"], data["b"], data["c"]), (true, false, true));
+        let err = Options::default()
+            .with_bools(&[crate::BoolStyle::TrueFalse])
+            .from_str::<HashMap<String, bool>>(input)
+            .unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ErrorKind::ParseAtomError(ParseAtomError::ExpectedBool(2..3))
+        );
+        assert_eq!(err.path(), Some("a"));
+    }
+
+    #[test]
+    fn read_unit_variant() {
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, PartialEq, Deserialize, serde_derive::Serialize)]
+        enum Level {
+            PV_LV_01_0,
+            PV_LV_09_5,
+        }
+        #[derive(Debug, PartialEq, Deserialize, serde_derive::Serialize)]
+        struct Difficulty {
+            level: Level,
+            levels: Vec<Level>,
+            version: u32,
+        }
+        let input = "level=PV_LV_09_5
+levels.0=PV_LV_01_0
+levels.1=PV_LV_09_5
+levels.length=2
+version=1
+";
+        let data: Difficulty = from_str(input).unwrap();
+        assert_eq!(
+            data,
+            Difficulty {
+                level: Level::PV_LV_09_5,
+                levels: vec![Level::PV_LV_01_0, Level::PV_LV_09_5],
+                version: 1,
+            }
+        );
+        assert_eq!(crate::to_string(&data).unwrap(), input);
+
+        #[derive(Debug, PartialEq, Deserialize, serde_derive::Serialize)]
+        struct Pv {
+            difficulty: HashMap<String, Vec<Entry>>,
+        }
+        #[derive(Debug, PartialEq, Deserialize, serde_derive::Serialize)]
+        struct Entry {
+            level: Level,
+            script_format: ScriptFormat,
+        }
+        #[derive(Debug, PartialEq, Deserialize, serde_derive::Serialize)]
+        struct ScriptFormat(String);
+        let input = "pv_001.difficulty.hard.0.level=PV_LV_09_5
+pv_001.difficulty.hard.0.script_format=0x14012316
+pv_001.difficulty.hard.length=1
+";
+        let data: HashMap<String, Pv> = from_str(input).unwrap();
+        let entry = &data["pv_001"].difficulty["hard"][0];
+        assert_eq!(entry.level, Level::PV_LV_09_5);
+        assert_eq!(entry.script_format, ScriptFormat("0x14012316".into()));
+        assert_eq!(crate::to_string(&data).unwrap(), input);
+
+        let input = "pv_001.difficulty.hard.0.level=PV_LV_99_9
+pv_001.difficulty.hard.0.script_format=0x14012316
+pv_001.difficulty.hard.length=1
+";
+        let err = from_str::<HashMap<String, Pv>>(input).unwrap_err();
+        assert_eq!(err.span().map(|x| &input[x]), Some("PV_LV_99_9"));
+        assert_eq!(err.path(), Some("pv_001.difficulty.hard.0.level"));
+
+        let input = "chart=(PV_LV_01_0, 3)
+";
+        let data: HashMap<String, (Level, u8)> = from_str(input).unwrap();
+        assert_eq!(data["chart"], (Level::PV_LV_01_0, 3));
+        assert_eq!(crate::to_string(&data).unwrap(), input);
+
+        #[derive(Debug, PartialEq, Deserialize, serde_derive::Serialize)]
+        enum Mixed {
+            Easy,
+            Custom(u8),
+        }
+        let input = "a=Easy
+b.Custom=5
+";
+        let data: HashMap<String, Mixed> = from_str(input).unwrap();
+        assert_eq!(data["a"], Mixed::Easy);
+        assert_eq!(data["b"], Mixed::Custom(5));
+        assert_eq!(crate::to_string(&data).unwrap(), input);
+    }
+
+    #[test]
+    fn read_error_path() {
+        #[allow(dead_code)]
+        #[derive(Debug, Deserialize)]
+        struct Pv {
+            difficulty: HashMap<String, Vec<Difficulty>>,
+        }
+        #[allow(dead_code)]
+        #[derive(Debug, Deserialize)]
+        struct Difficulty {
+            level: u32,
+        }
+        let input = "pv_123.difficulty.hard.0.level=high
+pv_123.difficulty.hard.length=1
+";
+        let err = from_str::<HashMap<String, Pv>>(input).unwrap_err();
+        assert_eq!(err.path(), Some("pv_123.difficulty.hard.0.level"));
+        assert_eq!(
+            err.to_string(),
+            "1:32: pv_123.difficulty.hard.0.level: Failed to parse an atom"
+        );
+        let err = from_reader::<_, HashMap<String, Pv>>(input.as_bytes()).unwrap_err();
+        assert_eq!(err.path(), Some("pv_123.difficulty.hard.0.level"));
+        let err = from_str::<HashMap<String, Pv>>("pv_123.difficulty.hard.0.song=1\n")
+            .unwrap_err();
+        assert_eq!(err.path(), Some("pv_123.difficulty.hard.0"));
+        assert_eq!(
+            err.to_string(),
+            "1:1: pv_123.difficulty.hard.0: missing field `level`"
+        );
+        let input = "pv_123.difficulty.hard.0.level=1
+pv_123.difficulty.hard.length=1
+pv_124.bpm=120
+";
+        let err = from_str::<HashMap<String, Pv>>(input).unwrap_err();
+        assert_eq!(err.span(), None);
+        assert_eq!(err.location().map(|x| x.line), Some(3));
+        assert_eq!(err.to_string(), "3:1: pv_124: missing field `difficulty`");
+    }
+
+    #[test]
+    fn read_recovering() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Pv {
+            bpm: u32,
+            difficulty: Vec<Difficulty>,
+        }
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Difficulty {
+            level: u32,
+        }
+        let input = "pv_001.bpm=120
+pv_001.difficulty.0.level=1
+pv_001.difficulty.1.level=hard
+pv_001.difficulty.2.level=3
+pv_001.difficulty.length=3
+pv_002.bpm=fast
+pv_002.difficulty.length=0
+pv_003 oops
+pv_004.difficulty.length=0
+";
+        let (pvs, errors) = from_str_recovering::<HashMap<String, Pv>>(input);
+        let pvs = pvs.unwrap();
+        let levels: Vec<_> = pvs["pv_001"].difficulty.iter().map(|x| x.level).collect();
+        assert_eq!(levels, vec![1, 3]);
+        assert_eq!(pvs.len(), 1);
+        let paths: Vec<_> = errors.iter().map(DeserializerError::path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                Some("pv_001.difficulty.1.level"),
+                Some("pv_002.bpm"),
+                None,
+                Some("pv_004"),
+            ]
+        );
+        assert_eq!(errors[0].location().map(|x| x.line), Some(3));
+        assert_eq!(
+            errors[2].kind(),
+            &ErrorKind::ExpectedKeyValuePair { span: 172..183 }
+        );
+        assert_eq!(errors[3].to_string(), "9:1: pv_004: missing field `bpm`");
+        assert_eq!(
+            crate::error::DeserializerErrors::from(errors).to_string(),
+            "Found 4 errors while reading"
+        );
+
+        let (pvs, errors) = from_str_recovering::<Pv>("bpm=fast\n");
+        assert_eq!(pvs, None);
+        let paths: Vec<_> = errors.iter().map(DeserializerError::path).collect();
+        assert_eq!(paths, vec![None, Some("bpm")]);
+        assert_eq!(errors[0].to_string(), "1:1: missing field `difficulty`");
+        let (pvs, errors) = from_str_recovering::<HashMap<String, u32>>("foo=1\nbar=2\n");
+        assert_eq!(pvs.map(|x| x.len()), Some(2));
+        assert!(errors.is_empty());
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn render_named() {
+        use miette::{GraphicalReportHandler, GraphicalTheme};
+
+        let input = "pv_001.bpm=120
+pv_001.song_name=Foo
+pv_001.bpm=150
+";
+        let err = from_str_named::<HashMap<String, HashMap<String, String>>>("pv_db.txt", input)
+            .unwrap_err();
+        let mut out = String::new();
+        GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+            .render_report(&mut out, &err)
+            .unwrap();
+        assert!(out.contains("pv_db.txt:3:1"), "{}", out);
+        assert!(out.contains("pv_001.bpm=150"), "{}", out);
+        assert!(out.contains("defined again here"), "{}", out);
+
+        let err = from_str_named::<HashMap<String, HashMap<String, u32>>>("pv_db.txt", input)
+            .unwrap_err();
+        let mut out = String::new();
+        GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+            .render_report(&mut out, &err)
+            .unwrap();
+        assert!(out.contains("pv_db.txt:2:18: pv_001.song_name:"), "{}", out);
+    }
+
     #[test]
     fn read_nested_struct() {
         let input = "
@@ -759,8 +1372,11 @@ trans.z.type=0
         }
         #[derive(Debug, PartialEq, Deserialize)]
         struct FieldOfView {
-            #[serde(rename = "fov_is_horizontal")]
-            horizontal: u8,
+            #[serde(
+                rename = "fov_is_horizontal",
+                deserialize_with = "crate::bools::deserialize"
+            )]
+            horizontal: bool,
             #[serde(rename = "fov")]
             value: KeySet,
         }
@@ -796,7 +1412,7 @@ trans.z.type=0
             view_point: ViewPoint {
                 aspect: 1.77778,
                 fov: FieldOfView {
-                    horizontal: 1,
+                    horizontal: true,
                     value: KeySet {
                         ty: 1,
                         value: 0.93616,
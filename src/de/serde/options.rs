@@ -0,0 +1,416 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::de::{Deserialize, DeserializeOwned};
+use serde_path_to_error::{Deserializer as Tracked, Track};
+
+use super::atom::Skipped;
+use super::{read, recover, Parser};
+use crate::bools::BoolStyle;
+use crate::de::{group_lines, keep_last, Deduplicate, Source};
+use crate::error::DeserializerError;
+#[cfg(doc)]
+use crate::error::ErrorKind;
+
+/// What to do when a sequence's `length` disagrees with the indices it defines.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum LengthCheck {
+    /// Log the mismatch and read every index that's there.
+    #[default]
+    Lenient,
+    /// Fail with [`ErrorKind::LengthMismatch`].
+    Strict,
+}
+
+/// What to do with a key that's defined by more than one line.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum DuplicateKeys {
+    /// Fail with [`ErrorKind::DuplicateKey`].
+    #[default]
+    Error,
+    /// Keep the first line and skip the others.
+    FirstWins,
+    /// Keep the last line and skip the others. This reads the whole input up front.
+    LastWins,
+}
+
+/// Which types are guessed from an atom when the type being read takes anything, like a
+/// [`crate::Value`] or a Python object.
+///
+/// Atoms are tried as a tuple, an integer, a float and a boolean, in that order, and are
+/// read as a string when nothing else fits or is allowed.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use serde_divatree::{Inference, Options};
+///
+/// #[derive(Debug, PartialEq, serde_derive::Deserialize)]
+/// #[serde(untagged)]
+/// enum Any {
+///     Int(i64),
+///     Float(f64),
+///     Str(String),
+/// }
+///
+/// let input = "id=007
+/// version=1.00
+/// ";
+/// let options = Options::default()
+///     .with_inference(Inference::default().with_floats(false).with_leading_zeros(true));
+/// let data: HashMap<String, Any> = options.from_str(input).unwrap();
+/// assert_eq!(data["id"], Any::Str("007".into()));
+/// assert_eq!(data["version"], Any::Str("1.00".into()));
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub struct Inference {
+    tuples: bool,
+    integers: bool,
+    floats: bool,
+    bools: bool,
+    leading_zeros: bool,
+    radix: bool,
+}
+
+impl Default for Inference {
+    /// Guesses every type, and reads leading zeros as numbers. Integers with a `0x`, `0o` or
+    /// `0b` prefix are kept as strings, so they're written back the way they were read.
+    fn default() -> Self {
+        Self {
+            tuples: true,
+            integers: true,
+            floats: true,
+            bools: true,
+            leading_zeros: false,
+            radix: false,
+        }
+    }
+}
+
+impl Inference {
+    /// Reads every atom as a string.
+    pub fn strings() -> Self {
+        Self {
+            tuples: false,
+            integers: false,
+            floats: false,
+            bools: false,
+            leading_zeros: false,
+            radix: false,
+        }
+    }
+
+    pub fn with_tuples(mut self, tuples: bool) -> Self {
+        self.tuples = tuples;
+        self
+    }
+
+    pub fn tuples(&self) -> bool {
+        self.tuples
+    }
+
+    /// Guesses integers, without a `0x`, `0o` or `0b` prefix unless [`Self::with_radix`] is
+    /// set too.
+    pub fn with_integers(mut self, integers: bool) -> Self {
+        self.integers = integers;
+        self
+    }
+
+    pub fn integers(&self) -> bool {
+        self.integers
+    }
+
+    /// Guesses floats, which turns a version like `1.00` into `1.0`.
+    pub fn with_floats(mut self, floats: bool) -> Self {
+        self.floats = floats;
+        self
+    }
+
+    pub fn floats(&self) -> bool {
+        self.floats
+    }
+
+    /// Guesses `true` and `false`, ignoring case.
+    pub fn with_bools(mut self, bools: bool) -> Self {
+        self.bools = bools;
+        self
+    }
+
+    pub fn bools(&self) -> bool {
+        self.bools
+    }
+
+    /// Keeps numbers with leading zeros, like the ID `007`, as strings.
+    pub fn with_leading_zeros(mut self, leading_zeros: bool) -> Self {
+        self.leading_zeros = leading_zeros;
+        self
+    }
+
+    pub fn leading_zeros(&self) -> bool {
+        self.leading_zeros
+    }
+
+    /// Guesses integers with a `0x`, `0o` or `0b` prefix too, like `0x14012316`. They lose
+    /// their prefix, and are written back in decimal.
+    pub fn with_radix(mut self, radix: bool) -> Self {
+        self.radix = radix;
+        self
+    }
+
+    pub fn radix(&self) -> bool {
+        self.radix
+    }
+}
+
+/// Settings for reading files that don't quite look like SEGA's own.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use serde_divatree::Options;
+///
+/// let input = "foo.bar=1
+/// baz.bar=2
+/// foo.baz=3
+/// ";
+/// let data: HashMap<String, HashMap<String, u32>> =
+///     Options::default().with_unordered(true).from_str(input).unwrap();
+/// assert_eq!(data["foo"]["baz"], 3);
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub struct Options {
+    unordered: bool,
+    duplicate_keys: DuplicateKeys,
+    length_check: LengthCheck,
+    bools: &'static [BoolStyle],
+    inference: Inference,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            unordered: false,
+            duplicate_keys: DuplicateKeys::default(),
+            length_check: LengthCheck::default(),
+            bools: BoolStyle::ALL,
+            inference: Inference::default(),
+        }
+    }
+}
+
+impl Options {
+    /// Groups every line by its key before parsing, so a node's children can be anywhere in
+    /// the file instead of following each other.
+    ///
+    /// This reads the whole input up front, and logs every node that was split up.
+    pub fn with_unordered(mut self, unordered: bool) -> Self {
+        self.unordered = unordered;
+        self
+    }
+
+    pub fn unordered(&self) -> bool {
+        self.unordered
+    }
+
+    /// Decides which line is read when the same key shows up more than once.
+    pub fn with_duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    pub fn duplicate_keys(&self) -> DuplicateKeys {
+        self.duplicate_keys
+    }
+
+    /// Decides whether a sequence whose `length` doesn't match its indices is an error.
+    pub fn with_length_check(mut self, length_check: LengthCheck) -> Self {
+        self.length_check = length_check;
+        self
+    }
+
+    pub fn length_check(&self) -> LengthCheck {
+        self.length_check
+    }
+
+    /// Decides which spellings are read as a `bool`, which is every [`BoolStyle`] by default.
+    ///
+    /// Other types never see these as booleans, so `1` still reads as an integer.
+    pub fn with_bools(mut self, bools: &'static [BoolStyle]) -> Self {
+        self.bools = bools;
+        self
+    }
+
+    pub fn bools(&self) -> &'static [BoolStyle] {
+        self.bools
+    }
+
+    /// Decides which types are guessed for types that take anything.
+    pub fn with_inference(mut self, inference: Inference) -> Self {
+        self.inference = inference;
+        self
+    }
+
+    pub fn inference(&self) -> Inference {
+        self.inference
+    }
+
+    pub fn from_str<'a, T>(self, s: &'a str) -> Result<T, DeserializerError>
+    where
+        T: Deserialize<'a>,
+    {
+        self.deserialize(s.lines(), Source::Str(s))
+    }
+
+    /// Like [`Options::from_str`], but errors keep `name` and `s` to show where they are.
+    pub fn from_str_named<'a, T>(self, name: &str, s: &'a str) -> Result<T, DeserializerError>
+    where
+        T: Deserialize<'a>,
+    {
+        self.from_str(s)
+            .map_err(|err| err.with_source_code(name, s))
+    }
+
+    /// Like [`Options::from_str`], but skips every broken subtree instead of stopping at the
+    /// first error. See [`crate::from_str_recovering`].
+    pub fn from_str_recovering<'a, T>(self, s: &'a str) -> (Option<T>, Vec<DeserializerError>)
+    where
+        T: Deserialize<'a>,
+    {
+        recover::from_str(s, self)
+    }
+
+    pub fn from_slice<'a, T>(self, v: &'a [u8]) -> Result<T, DeserializerError>
+    where
+        T: Deserialize<'a>,
+    {
+        self.from_str(std::str::from_utf8(v)?)
+    }
+
+    pub fn from_reader<R, T>(self, reader: R) -> Result<T, DeserializerError>
+    where
+        R: std::io::Read,
+        T: DeserializeOwned,
+    {
+        read::from_reader(reader, self)
+    }
+
+    pub fn from_lines<I, T>(self, lines: I) -> Result<T, DeserializerError>
+    where
+        I: IntoIterator<Item = String>,
+        T: DeserializeOwned,
+    {
+        read::from_lines(lines, self)
+    }
+
+    /// Parses `lines` after applying every option to them.
+    pub(super) fn deserialize<'a, I, T>(
+        self,
+        lines: I,
+        source: Source<'a>,
+    ) -> Result<T, DeserializerError>
+    where
+        I: Iterator<Item = &'a str> + 'a,
+        T: Deserialize<'a>,
+    {
+        self.deserialize_skipping(lines, source, None)
+    }
+
+    /// Like [`Options::deserialize`], but atoms that can't be read are put in `skipped` and
+    /// read as a placeholder instead of failing.
+    pub(super) fn deserialize_skipping<'a, I, T>(
+        self,
+        lines: I,
+        source: Source<'a>,
+        skipped: Option<Skipped>,
+    ) -> Result<T, DeserializerError>
+    where
+        I: Iterator<Item = &'a str> + 'a,
+        T: Deserialize<'a>,
+    {
+        let lines = lines
+            .filter(|x| !x.trim().is_empty())
+            .filter(|x| !x.starts_with('#'));
+        let error = Rc::new(RefCell::new(None));
+        let mut track = Track::new();
+        // The span of the line read last, for errors that don't know where they are
+        let (val, last_span) = if self.unordered || self.duplicate_keys == DuplicateKeys::LastWins {
+            let lines = self.arrange(lines, source);
+            let iter = Deduplicate::new(
+                lines.into_iter(),
+                self.duplicate_keys,
+                source,
+                Rc::clone(&error),
+            );
+            let mut parser = Parser::with_options(iter, self)
+                .with_source(source)
+                .with_skipped(skipped);
+            let val = T::deserialize(Tracked::new(&mut parser, &mut track));
+            (val, parser.last_span())
+        } else {
+            let iter = Deduplicate::new(lines, self.duplicate_keys, source, Rc::clone(&error));
+            let mut parser = Parser::with_options(iter, self)
+                .with_source(source)
+                .with_skipped(skipped);
+            let val = T::deserialize(Tracked::new(&mut parser, &mut track));
+            (val, parser.last_span())
+        };
+        // A duplicate looks like the end of the input to the parser, so it takes precedence.
+        let err = error.borrow_mut().take();
+        match err {
+            Some(err) => Err(err.locate(source)),
+            None => val.map_err(|err| {
+                err.with_path(&track.path())
+                    .locate(source)
+                    .or_location(last_span, source)
+            }),
+        }
+    }
+
+    /// Applies every option to `lines` up front, for building a [`crate::Value`].
+    pub(crate) fn lines<'a>(
+        self,
+        lines: impl Iterator<Item = &'a str>,
+        source: Source<'a>,
+    ) -> Result<Vec<&'a str>, DeserializerError> {
+        let lines = lines
+            .filter(|x| !x.trim().is_empty())
+            .filter(|x| !x.starts_with('#'));
+        let error = Rc::new(RefCell::new(None));
+        let lines = self.arrange(lines, source);
+        let lines = Deduplicate::new(
+            lines.into_iter(),
+            self.duplicate_keys,
+            source,
+            Rc::clone(&error),
+        )
+        .collect();
+        let err = error.borrow_mut().take();
+        match err {
+            Some(err) => Err(err.locate(source)),
+            None => Ok(lines),
+        }
+    }
+
+    /// Reorders `lines` for the options that need to see all of them first.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn arrange<'a>(self, lines: impl Iterator<Item = &'a str>, source: Source<'a>) -> Vec<&'a str> {
+        let mut lines: Vec<_> = lines.collect();
+        if self.unordered {
+            #[cfg(feature = "tracing")]
+            for key in crate::de::find_scattered(lines.iter().copied(), source) {
+                tracing::warn!(
+                    path = key.path.as_str(),
+                    span = tracing::field::debug(&key.span),
+                    "The children of this key are not contiguous."
+                );
+            }
+            lines = group_lines(lines.into_iter());
+        }
+        if self.duplicate_keys == DuplicateKeys::LastWins {
+            lines = keep_last(lines);
+        }
+        lines
+    }
+}
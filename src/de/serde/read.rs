@@ -0,0 +1,137 @@
+//! Reading from owned input, such as an [`std::io::Read`].
+//!
+//! The parser borrows every line it reads from a single string, so the input is read into
+//! one first and parsed the same way as [`crate::from_str`].
+use std::io::Read;
+
+use serde::de::DeserializeOwned;
+
+use super::Options;
+use crate::error::DeserializerError;
+
+pub(super) fn from_reader<R, T>(mut reader: R, options: Options) -> Result<T, DeserializerError>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    options.from_str(&s)
+}
+
+pub(super) fn from_lines<I, T>(lines: I, options: Options) -> Result<T, DeserializerError>
+where
+    I: IntoIterator<Item = String>,
+    T: DeserializeOwned,
+{
+    let mut s = String::new();
+    for line in lines {
+        s.push_str(&line);
+        s.push('\n');
+    }
+    options.from_str(&s)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+
+    use serde_derive::Deserialize;
+
+    use super::*;
+    use crate::error::{ErrorKind, Location};
+    use crate::{from_lines, from_reader};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Test {
+        foo: u32,
+        list: Vec<String>,
+        inner: HashMap<String, f32>,
+    }
+
+    const INPUT: &str = "# comment
+foo=32
+inner.bar=1.5
+inner.baz=2
+
+list.0=Hello
+list.1=World!
+list.length=2
+";
+
+    fn expected() -> Test {
+        let mut inner = HashMap::new();
+        inner.insert("bar".to_string(), 1.5);
+        inner.insert("baz".to_string(), 2.0);
+        Test {
+            foo: 32,
+            list: vec!["Hello".to_string(), "World!".to_string()],
+            inner,
+        }
+    }
+
+    #[test]
+    fn read_reader() {
+        assert_eq!(from_reader(INPUT.as_bytes()), Ok(expected()));
+        let crlf = INPUT.replace('\n', "\r\n");
+        assert_eq!(from_reader(crlf.as_bytes()), Ok(expected()));
+    }
+
+    #[test]
+    fn read_lines() {
+        let lines = INPUT.lines().map(String::from).collect::<Vec<_>>();
+        assert_eq!(from_lines(lines), Ok(expected()));
+    }
+
+    #[test]
+    fn read_reader_unordered() {
+        let unordered = "list.1=World!
+inner.bar=1.5
+list.length=2
+foo=32
+list.0=Hello
+inner.baz=2
+";
+        let options = Options::default().with_unordered(true);
+        assert_eq!(options.from_reader(unordered.as_bytes()), Ok(expected()));
+    }
+
+    #[test]
+    fn read_reader_duplicate_keys() {
+        let input = "# comment\r\nfoo=1\r\nbar=2\r\n foo = 3\r\n";
+        match from_reader::<_, HashMap<String, u32>>(input.as_bytes())
+            .map_err(DeserializerError::into_kind)
+        {
+            Err(ErrorKind::DuplicateKey { first, second, .. }) => {
+                assert_eq!(&input[first], "foo");
+                assert_eq!(&input[second], "foo");
+            }
+            e => unreachable!("{:?}", e),
+        }
+        let err = from_reader::<_, HashMap<String, u32>>(input.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.location(),
+            Some(Location {
+                line: 4,
+                column: 2,
+                byte: 26
+            })
+        );
+    }
+
+    #[test]
+    fn read_reader_error() {
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"))
+            }
+        }
+        match from_reader::<_, HashMap<String, u32>>(Failing).map_err(DeserializerError::into_kind)
+        {
+            Err(ErrorKind::Io { kind, .. }) => assert_eq!(kind, io::ErrorKind::BrokenPipe),
+            e => unreachable!("{:?}", e),
+        }
+    }
+}
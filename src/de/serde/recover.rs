@@ -0,0 +1,161 @@
+//! Reading as much as possible from a file with errors in it.
+//!
+//! Lines that aren't `key=value` are left out up front. An atom that can't be read is put
+//! aside by the parser, which reads a placeholder in its place and keeps going, so a single
+//! pass finds every one of them. The input is then read again without them, so the result
+//! doesn't have any placeholders in it.
+//!
+//! An error from the type being read, like a missing field, stops the pass instead, as a
+//! visitor that failed can't be resumed. The input is read again without the subtree of that
+//! error. An error caused by a dropped subtree, like a missing field, drops its parent in turn
+//! instead of being reported again.
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::rc::Rc;
+
+use serde::de::Deserialize;
+
+use super::atom::Skipped;
+use super::Options;
+use crate::de::{KeyValue, Source};
+use crate::error::{DeserializerError, ErrorKind};
+
+pub(super) fn from_str<'a, T>(s: &'a str, options: Options) -> (Option<T>, Vec<DeserializerError>)
+where
+    T: Deserialize<'a>,
+{
+    let source = Source::Str(s);
+    let mut errors = Vec::new();
+    let mut lines = Vec::new();
+    for line in s
+        .lines()
+        .filter(|x| !x.trim().is_empty())
+        .filter(|x| !x.starts_with('#'))
+    {
+        if KeyValue::new(line).is_some() {
+            lines.push(line);
+        } else {
+            let err = ErrorKind::ExpectedKeyValuePair {
+                span: source.span(line),
+            };
+            errors.push(DeserializerError::from(err).locate(source));
+        }
+    }
+    // The keys dropped after the last pass, to tell the errors they cause apart
+    let mut dropped: Vec<Vec<String>> = Vec::new();
+    let value = loop {
+        let skipped = Skipped::default();
+        let result = options.deserialize_skipping(
+            lines.clone().into_iter(),
+            source,
+            Some(Rc::clone(&skipped)),
+        );
+        // The keys to drop before the next pass
+        let mut keys: Vec<Vec<String>> = Vec::new();
+        // The lines of the atoms that were skipped
+        let mut placeholders = BTreeSet::new();
+        // A line can be read more than once, like when a sequence is looked up out of order
+        let mut skipped = skipped.take();
+        skipped.sort_by_key(|x| x.span().start);
+        skipped.dedup();
+        for err in skipped {
+            let index = line_of(&lines, source, err.span());
+            let key: Vec<String> = index
+                .and_then(|i| KeyValue::new(lines[i]))
+                .map_or_else(Vec::new, |kv| kv.path().map(String::from).collect());
+            errors.push(
+                DeserializerError::from(err)
+                    .with_key(&key.join("."))
+                    .locate(source),
+            );
+            placeholders.extend(index);
+            keys.push(key);
+        }
+        // An error about a dropped child, which isn't reported in case dropping doesn't help
+        let mut cascade = None;
+        match result {
+            Ok(value) if keys.is_empty() => break Some(value),
+            Ok(_) => {}
+            // The placeholder of a skipped atom was turned down as well
+            Err(err)
+                if err
+                    .span()
+                    .and_then(|x| line_of(&lines, source, x))
+                    .is_some_and(|x| placeholders.contains(&x)) => {}
+            Err(err) => {
+                let line = err.span().and_then(|x| line_of(&lines, source, x));
+                let path: Vec<String> = err
+                    .path()
+                    .map_or_else(Vec::new, |x| x.split('.').map(String::from).collect());
+                // An error without a span right after dropping a child is most likely about
+                // the child
+                let parent = match line {
+                    None => dropped
+                        .iter()
+                        .find(|key| within(&path, key))
+                        .map(|key| key[..path.len()].to_vec()),
+                    Some(_) => None,
+                };
+                let key = match (line, parent) {
+                    (Some(index), _) => KeyValue::new(lines[index]).map_or_else(Vec::new, |kv| {
+                        kv.path()
+                            .take(path.len().max(1))
+                            .map(String::from)
+                            .collect()
+                    }),
+                    (None, Some(key)) => {
+                        cascade = Some(err);
+                        key
+                    }
+                    (None, None) => {
+                        errors.push(err);
+                        path
+                    }
+                };
+                if key.is_empty() {
+                    // The root can't be skipped, so there's nothing left to read
+                    break None;
+                }
+                keys.push(key);
+            }
+        }
+        let joined: BTreeSet<_> = keys.iter().map(|x| x.join(".")).collect();
+        let before = lines.len();
+        lines.retain(|x| !KeyValue::new(x).is_some_and(|kv| is_dropped(kv.key, &joined)));
+        if lines.len() == before {
+            // Reading the same lines again would fail the same way
+            errors.extend(cascade);
+            break None;
+        }
+        dropped = keys;
+    };
+    errors.sort_by_key(|x| x.location().map_or(usize::MAX, |x| x.byte));
+    (value, errors)
+}
+
+/// The index of the line in `lines` that `span` is on, where `lines` are in the order of the
+/// input.
+fn line_of(lines: &[&str], source: Source, span: Range<usize>) -> Option<usize> {
+    let index = lines
+        .partition_point(|x| source.span(x).start <= span.start)
+        .checked_sub(1)?;
+    let line = source.span(lines[index]);
+    (line.contains(&span.start) || line.end == span.start).then_some(index)
+}
+
+/// Whether `key` or one of its parents is in `keys`.
+fn is_dropped(key: &str, keys: &BTreeSet<String>) -> bool {
+    key.match_indices(KeyValue::PATH_DELIMITER)
+        .map(|(i, _)| &key[..i])
+        .chain(std::iter::once(key))
+        .any(|x| keys.contains(x))
+}
+
+/// Whether the key path `path` is a parent of `key`, where indices can be renumbered.
+fn within(path: &[String], key: &[String]) -> bool {
+    path.len() < key.len()
+        && path
+            .iter()
+            .zip(key)
+            .all(|(a, b)| a == b || (a.parse::<usize>().is_ok() && b.parse::<usize>().is_ok()))
+}
@@ -0,0 +1,377 @@
+//! Which keys changed between two versions of a file.
+//!
+//! Unlike a diff of the lines, this compares the trees the files describe, so reordering
+//! lines changes nothing. Sequences are lined up by their elements instead of their indices,
+//! so inserting an element shows up as one added element rather than every element after it
+//! changing, and a sequence that grows or shrinks shows its `length` changing.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_divatree::diff::{self, Change};
+//! use serde_divatree::Value;
+//!
+//! let old: Value = "pv_001.bpm=120\npv_001.song_name=Foo\n".parse().unwrap();
+//! let new: Value = "pv_001.song_name=Foo\npv_001.bpm=150\n".parse().unwrap();
+//! let diff = diff::diff(&old, &new);
+//! assert_eq!(
+//!     diff.changes,
+//!     [Change::Changed {
+//!         path: "pv_001.bpm".into(),
+//!         old: "120".into(),
+//!         new: "150".into(),
+//!     }]
+//! );
+//! assert_eq!(diff.to_string(), "@@ pv_001 @@\n-pv_001.bpm=120\n+pv_001.bpm=150\n");
+//! ```
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+
+use crate::de::KeyValue;
+use crate::value::{Seq, Value};
+
+/// Sequences with more pairs of elements than this are compared index by index, since lining
+/// them up takes quadratic memory.
+const MAX_ALIGNED: usize = 1 << 20;
+
+/// A single key that differs, along with its values.
+///
+/// Keys are full dotted paths, and every value is an atom. A node that's added or removed
+/// shows up as every key under it.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum Change {
+    /// A key that's only in the new file.
+    Added { path: String, value: String },
+    /// A key that's only in the old file, at its path there.
+    Removed { path: String, value: String },
+    /// A key whose value changed, at its path in the new file.
+    Changed {
+        path: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Added { path, .. } | Self::Removed { path, .. } | Self::Changed { path, .. } => {
+                path
+            }
+        }
+    }
+}
+
+impl Display for Change {
+    /// Writes the change as the lines a unified diff would show for it.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { path, value } => writeln!(f, "+{}={}", path, value),
+            Self::Removed { path, value } => writeln!(f, "-{}={}", path, value),
+            Self::Changed { path, old, new } => {
+                writeln!(f, "-{}={}", path, old)?;
+                writeln!(f, "+{}={}", path, new)
+            }
+        }
+    }
+}
+
+/// Every change between two trees, in the order of their keys.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Hash)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Writes the diff like a unified diff of the files `old` and `new`.
+    pub fn to_unified(&self, old: &str, new: &str) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+        format!("--- {}\n+++ {}\n{}", old, new, self)
+    }
+}
+
+impl Display for Diff {
+    /// Writes every change, under a `@@ key @@` header for each top level key.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut hunk = None;
+        for change in &self.changes {
+            let root = change
+                .path()
+                .split(KeyValue::PATH_DELIMITER)
+                .next()
+                .unwrap_or_default();
+            if hunk != Some(root) {
+                writeln!(f, "@@ {} @@", root)?;
+                hunk = Some(root);
+            }
+            write!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares `old` to `new`.
+pub fn diff(old: &Value, new: &Value) -> Diff {
+    let mut changes = Vec::new();
+    diff_node(old, new, "", &mut changes);
+    Diff { changes }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}{}{}", path, KeyValue::PATH_DELIMITER, key)
+    }
+}
+
+fn diff_node(old: &Value, new: &Value, path: &str, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Map(old), Value::Map(new)) => {
+            let keys: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let path = join(path, key);
+                match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => diff_node(old, new, &path, changes),
+                    (Some(old), None) => removed(old, &path, changes),
+                    (None, Some(new)) => added(new, &path, changes),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Seq(old), Value::Seq(new)) => diff_seq(old, new, path, changes),
+        (old, new) if old == new => {}
+        (old, new) => match (old.to_atom(), new.to_atom()) {
+            (Some(old), Some(new)) => changes.push(Change::Changed {
+                path: path.to_string(),
+                old,
+                new,
+            }),
+            _ => {
+                removed(old, path, changes);
+                added(new, path, changes);
+            }
+        },
+    }
+}
+
+/// Compares two sequences, lining up the elements they have in common first.
+///
+/// Elements are written at the index they have in their own file.
+fn diff_seq(old_seq: &Seq, new_seq: &Seq, path: &str, changes: &mut Vec<Change>) {
+    let old: Vec<_> = old_seq.iter().collect();
+    let new: Vec<_> = new_seq.iter().collect();
+    let at = |index: usize| join(path, &index.to_string());
+    let mut matches = if old.len().saturating_mul(new.len()) <= MAX_ALIGNED {
+        common(&old, &new)
+    } else {
+        Vec::new()
+    };
+    matches.push((old.len(), new.len()));
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in matches {
+        // The elements between two matches are compared pairwise, and the rest are
+        // added or removed
+        let paired = (next_i - i).min(next_j - j);
+        for k in 0..paired {
+            let ((old_index, old), (new_index, new)) = (old[i + k], new[j + k]);
+            if old_index == new_index {
+                diff_node(old, new, &at(new_index), changes);
+            } else {
+                removed(old, &at(old_index), changes);
+                added(new, &at(new_index), changes);
+            }
+        }
+        for (index, old) in &old[i + paired..next_i] {
+            removed(old, &at(*index), changes);
+        }
+        for (index, new) in &new[j + paired..next_j] {
+            added(new, &at(*index), changes);
+        }
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+    let old_ender = join(path, old_seq.ender());
+    let new_ender = join(path, new_seq.ender());
+    match (old_seq.length(), new_seq.length()) {
+        (Some(old), Some(new)) if old_ender == new_ender => {
+            if old != new {
+                changes.push(Change::Changed {
+                    path: new_ender,
+                    old: old.to_string(),
+                    new: new.to_string(),
+                });
+            }
+        }
+        (old, new) => {
+            if let Some(old) = old {
+                changes.push(Change::Removed {
+                    path: old_ender,
+                    value: old.to_string(),
+                });
+            }
+            if let Some(new) = new {
+                changes.push(Change::Added {
+                    path: new_ender,
+                    value: new.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// The positions of the longest run of elements both sequences have in the same order.
+fn common(old: &[(usize, &Value)], new: &[(usize, &Value)]) -> Vec<(usize, usize)> {
+    // lengths[i][j] is the length of the longest run in `old[i..]` and `new[j..]`
+    let width = new.len() + 1;
+    let mut lengths = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i].1 == new[j].1 {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i].1 == new[j].1 {
+            matches.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+fn added(value: &Value, path: &str, changes: &mut Vec<Change>) {
+    leaves(value, path, &mut |path, value| {
+        changes.push(Change::Added { path, value })
+    });
+}
+
+fn removed(value: &Value, path: &str, changes: &mut Vec<Change>) {
+    leaves(value, path, &mut |path, value| {
+        changes.push(Change::Removed { path, value })
+    });
+}
+
+/// Calls `f` with every line `value` is written as.
+fn leaves(value: &Value, path: &str, f: &mut impl FnMut(String, String)) {
+    match value {
+        Value::Map(map) => {
+            for (key, value) in map {
+                leaves(value, &join(path, key), f);
+            }
+        }
+        Value::Seq(seq) => {
+            for (index, value) in seq.iter() {
+                leaves(value, &join(path, &index.to_string()), f);
+            }
+            if let Some(length) = seq.length() {
+                f(join(path, seq.ender()), length.to_string());
+            }
+        }
+        value => {
+            if let Some(atom) = value.to_atom() {
+                f(path.to_string(), atom);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_pv_db() {
+        let old: Value = "pv_001.bpm=120
+pv_001.difficulty.hard.0.level=PV_LV_07_0
+pv_001.difficulty.hard.1.level=PV_LV_08_0
+pv_001.difficulty.hard.length=2
+pv_001.song_name=Foo
+pv_002.bpm=90
+pv_002.lyric.0=La
+pv_002.lyric.length=1
+"
+        .parse()
+        .unwrap();
+        // Reordered, with an element inserted at the start of a sequence
+        let new: Value = "pv_001.song_name.en=Foo
+pv_001.difficulty.hard.0.level=PV_LV_06_0
+pv_001.difficulty.hard.1.level=PV_LV_07_0
+pv_001.difficulty.hard.2.level=PV_LV_08_0
+pv_001.difficulty.hard.length=3
+pv_001.bpm=150
+pv_003.bpm=(1, 2)
+"
+        .parse()
+        .unwrap();
+        let diff = diff(&old, &new);
+        let expected = "--- pv_db.txt
++++ mod_pv_db.txt
+@@ pv_001 @@
+-pv_001.bpm=120
++pv_001.bpm=150
++pv_001.difficulty.hard.0.level=PV_LV_06_0
+-pv_001.difficulty.hard.length=2
++pv_001.difficulty.hard.length=3
+-pv_001.song_name=Foo
++pv_001.song_name.en=Foo
+@@ pv_002 @@
+-pv_002.bpm=90
+-pv_002.lyric.0=La
+-pv_002.lyric.length=1
+@@ pv_003 @@
++pv_003.bpm=(1, 2)
+";
+        assert_eq!(diff.to_unified("pv_db.txt", "mod_pv_db.txt"), expected);
+        assert_eq!(
+            diff.changes[1],
+            Change::Added {
+                path: "pv_001.difficulty.hard.0.level".into(),
+                value: "PV_LV_06_0".into(),
+            }
+        );
+        assert!(super::diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn diff_seq_pairs() {
+        let old: Value = "0=a\n1=b\n2=c\nlength=3\n".parse().unwrap();
+        let new: Value = "0=a\n1=x\n2=c\n3=d\nlength=4\n".parse().unwrap();
+        let diff = diff(&old, &new);
+        let paths: Vec<_> = diff.changes.iter().map(Change::path).collect();
+        assert_eq!(paths, ["1", "3", "length"]);
+        assert_eq!(
+            diff.changes[0],
+            Change::Changed {
+                path: "1".into(),
+                old: "b".into(),
+                new: "x".into(),
+            }
+        );
+
+        // Elements keep the index they have in their own file
+        let old: Value = "0=a\n5=b\nnum=6\n".parse().unwrap();
+        let new: Value = "0=a\n7=c\nlength=8\n".parse().unwrap();
+        let diff = super::diff(&old, &new);
+        assert_eq!(
+            diff.to_string(),
+            "@@ 5 @@\n-5=b\n@@ 7 @@\n+7=c\n@@ num @@\n-num=6\n@@ length @@\n+length=8\n"
+        );
+    }
+}
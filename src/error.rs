@@ -6,6 +6,9 @@ use thiserror::*;
 use std::fmt::{self, Display};
 use std::num::{ParseFloatError, ParseIntError};
 use std::ops::Range;
+use std::sync::Arc;
+
+use crate::de::Source;
 
 #[derive(Debug, Default, Error, PartialEq, Eq, PartialOrd, Ord, Clone)]
 #[error("Syntax error in line {line_num}. `{line}`")]
@@ -14,30 +17,341 @@ pub struct ParseError {
     pub line: String,
 }
 
+/// An index that a sequence can't hold, as its length wouldn't fit in a `usize`.
+#[derive(Debug, Error, PartialEq, Eq, Clone, Copy)]
+#[error("The index {0} is too large for a sequence")]
+pub struct IndexOverflow(pub usize);
+
+/// Where an error is in the input.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub struct Location {
+    /// The line, starting from 1
+    pub line: usize,
+    /// The character in the line, starting from 1
+    pub column: usize,
+    /// The offset in bytes since the beginning of the input
+    pub byte: usize,
+}
+
+impl Location {
+    /// Finds the line and column of `byte` in `input`.
+    pub fn new(input: &str, byte: usize) -> Self {
+        let before = input.get(..byte).unwrap_or(input);
+        let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            byte,
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The name and contents of the file an error was found in.
+#[derive(Debug, PartialEq, Eq)]
+struct SourceFile {
+    name: String,
+    text: String,
+}
+
+#[cfg(feature = "miette")]
+impl miette::SourceCode for SourceFile {
+    fn read_span<'a>(
+        &'a self,
+        span: &miette::SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn miette::SpanContents<'a> + 'a>, miette::MietteError> {
+        let contents = self
+            .text
+            .read_span(span, context_lines_before, context_lines_after)?;
+        Ok(Box::new(miette::MietteSpanContents::new_named(
+            self.name.clone(),
+            contents.data(),
+            *contents.span(),
+            contents.line(),
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+/// An error found while reading a file, along with where it was found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DeserializerError {
+    kind: Box<ErrorKind>,
+    location: Option<Location>,
+    /// The key path that was being read, like `pv_001.difficulty.hard.0.level`
+    path: Option<String>,
+    file: Option<Arc<SourceFile>>,
+}
+
+impl DeserializerError {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> ErrorKind {
+        *self.kind
+    }
+
+    /// Where the error is, if the input it came from is known.
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+
+    /// The bytes the error points at.
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.kind.span()
+    }
+
+    /// The key path that was being read when the error happened, like
+    /// `pv_001.difficulty.hard.0.level`.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// The name of the file the error was found in, if it was given one.
+    pub fn file_name(&self) -> Option<&str> {
+        self.file.as_ref().map(|x| x.name.as_str())
+    }
+
+    /// The contents of the file the error was found in, if it was given one.
+    pub fn source_text(&self) -> Option<&str> {
+        self.file.as_ref().map(|x| x.text.as_str())
+    }
+
+    /// Keeps the file the error was found in, so it can be shown along with the error.
+    ///
+    /// With the `miette` feature, this is the source code every label points into.
+    pub fn with_source_code(mut self, name: impl Into<String>, text: impl Into<String>) -> Self {
+        let text = text.into();
+        if self.location.is_none() {
+            self.location = self.span().map(|x| Location::new(&text, x.start));
+        }
+        self.file = Some(Arc::new(SourceFile {
+            name: name.into(),
+            text,
+        }));
+        self
+    }
+
+    /// Keeps the key path tracked up to the error, unless it already has one.
+    pub(crate) fn with_path(mut self, path: &serde_path_to_error::Path) -> Self {
+        if self.path.is_none() {
+            let path = key_path(path);
+            self.path = (!path.is_empty()).then_some(path);
+        }
+        self
+    }
+
+    /// Keeps `key` as the key path of the error, unless it already has one.
+    pub(crate) fn with_key(mut self, key: &str) -> Self {
+        if self.path.is_none() && !key.is_empty() {
+            self.path = Some(key.to_string());
+        }
+        self
+    }
+
+    /// Finds the location of the error in `source`, unless it already has one.
+    pub(crate) fn locate(mut self, source: Source) -> Self {
+        if self.location.is_none() {
+            self.location = self.span().and_then(|x| source.location(x.start));
+        }
+        self
+    }
+
+    /// Points an error without a span, like a missing field, at `span` in `source`.
+    pub(crate) fn or_location(mut self, span: Range<usize>, source: Source) -> Self {
+        if self.location.is_none() && self.span().is_none() {
+            self.location = source.location(span.start);
+        }
+        self
+    }
+}
+
+/// Writes `path` the way keys are written in a file, like `pv_001.difficulty.hard.0.level`.
+///
+/// A key that couldn't be read is left out, so the path ends at its parent.
+pub(crate) fn key_path(path: &serde_path_to_error::Path) -> String {
+    use serde_path_to_error::Segment;
+
+    path.iter()
+        .filter_map(|segment| match segment {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.clone()),
+            Segment::Enum { variant } => Some(variant.clone()),
+            Segment::Unknown => None,
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl Display for DeserializerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.file_name() {
+            write!(f, "{}:", name)?;
+        }
+        match self.location {
+            Some(location) => write!(f, "{}: ", location)?,
+            None if self.file.is_some() => f.write_str(" ")?,
+            None => {}
+        }
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path)?;
+        }
+        self.kind.fmt(f)
+    }
+}
+
+impl std::error::Error for DeserializerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.kind.source()
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Diagnostic for DeserializerError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.kind.code()
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        self.kind.severity()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.kind.help()
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.kind.url()
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        match &self.file {
+            Some(file) => Some(&**file),
+            None => self.kind.source_code(),
+        }
+    }
+
+    /// The labels of the atom are shown along with the error, instead of in a nested one.
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match &*self.kind {
+            ErrorKind::ParseAtomError(err) => err.labels(),
+            kind => kind.labels(),
+        }
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        self.kind.related()
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.kind.diagnostic_source()
+    }
+}
+
+impl<T> From<T> for DeserializerError
+where
+    ErrorKind: From<T>,
+{
+    fn from(err: T) -> Self {
+        Self {
+            kind: Box::new(err.into()),
+            location: None,
+            path: None,
+            file: None,
+        }
+    }
+}
+
+impl de::Error for DeserializerError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ErrorKind::Custom(msg.to_string()).into()
+    }
+}
+
+/// Every error found while reading a file with [`crate::from_str_recovering`], as a single
+/// report.
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+#[error("Found {} errors while reading", .errors.len())]
+pub struct DeserializerErrors {
+    #[cfg_attr(feature = "miette", related)]
+    pub errors: Vec<DeserializerError>,
+}
+
+impl From<Vec<DeserializerError>> for DeserializerErrors {
+    fn from(errors: Vec<DeserializerError>) -> Self {
+        Self { errors }
+    }
+}
+
+/// Everything that can go wrong while reading a file.
 #[derive(Debug, Error, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "miette", derive(Diagnostic))]
 #[cfg_attr(
     feature = "miette",
     diagnostic(help("maybe if you stanned loona you wouldn't have broken this 💅"))
 )]
-pub enum DeserializerError {
+pub enum ErrorKind {
     #[error("An internal parser error occured.")]
     ParseError(#[from] ParseError),
     #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::key_value)))]
     #[error("Expected a key value pair")]
-    ExpectedKeyValuePair,
+    ExpectedKeyValuePair {
+        #[cfg_attr(feature = "miette", label("This isn't `key=value`"))]
+        span: Range<usize>,
+    },
     #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::value)))]
     #[error("Expected a value node. Found a key node instead.")]
-    ExpectedValueNode,
+    ExpectedValueNode {
+        #[cfg_attr(feature = "miette", label("This is a key node"))]
+        span: Range<usize>,
+    },
     #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::key)))]
     #[error("Expected a key node. Found a value node instead.")]
-    ExpectedKeyNode,
+    ExpectedKeyNode {
+        #[cfg_attr(feature = "miette", label("This is a value node"))]
+        span: Range<usize>,
+    },
     #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::sequence)))]
     #[error("Expected a sequence, found something else")]
     ExpectedSequenece {
         #[cfg_attr(feature = "miette", label("This was unexpected."))]
         unexpected: Range<usize>,
     },
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::index)))]
+    #[error("The index is too large for a sequence")]
+    IndexOverflow {
+        #[cfg_attr(feature = "miette", label("This index"))]
+        span: Range<usize>,
+    },
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::duplicate_key)))]
+    #[error("The key `{key}` is defined more than once")]
+    DuplicateKey {
+        key: String,
+        #[cfg_attr(feature = "miette", label("first defined here"))]
+        first: Range<usize>,
+        #[cfg_attr(feature = "miette", label("defined again here"))]
+        second: Range<usize>,
+    },
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::length)))]
+    #[error("The sequence declares a length of {length} but defines {found} elements")]
+    LengthMismatch {
+        length: i64,
+        found: usize,
+        /// The indices defined at or past `length`
+        extra: Vec<i64>,
+        #[cfg_attr(feature = "miette", label("declared here"))]
+        span: Range<usize>,
+    },
     #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::atom)))]
     #[error("Failed to parse an atom")]
     ParseAtomError(
@@ -45,12 +359,103 @@ pub enum DeserializerError {
         #[cfg_attr(feature = "miette", diagnostic_source)]
         super::de::serde::atom::ParseAtomError,
     ),
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::utf8)))]
+    #[error("The input isn't valid UTF-8")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::parser::io)))]
+    #[error("Failed to read the input: {message}")]
+    Io {
+        kind: std::io::ErrorKind,
+        message: String,
+    },
     #[error("{0}")]
     Custom(String),
 }
 
-impl de::Error for DeserializerError {
+impl ErrorKind {
+    /// The bytes the error points at.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Self::ExpectedKeyValuePair { span }
+            | Self::ExpectedValueNode { span }
+            | Self::ExpectedKeyNode { span }
+            | Self::IndexOverflow { span }
+            | Self::LengthMismatch { span, .. } => Some(span.clone()),
+            Self::ExpectedSequenece { unexpected } => Some(unexpected.clone()),
+            Self::DuplicateKey { second, .. } => Some(second.clone()),
+            Self::ParseAtomError(err) => Some(err.span()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ErrorKind {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io {
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+#[cfg_attr(
+    feature = "miette",
+    diagnostic(help("maybe if you stanned loona you wouldn't have broken this 💅"))
+)]
+pub enum SerializerError {
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::serializer::atom)))]
+    #[error("Expected a value that fits in a single atom, found a {0}")]
+    ExpectedAtom(&'static str),
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::serializer::key)))]
+    #[error("The key `{0}` cannot be represented in a key path")]
+    InvalidKey(String),
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::serializer::value)))]
+    #[error("The value `{0}` would be read back differently, as it has `=` in it or whitespace around it")]
+    InvalidValue(String),
+    #[cfg_attr(
+        feature = "miette",
+        diagnostic(code(divatree::serializer::unsupported))
+    )]
+    #[error("Serializing a {0} is not supported")]
+    Unsupported(&'static str),
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::serializer::top_level)))]
+    #[error("Expected a map, struct or sequence at the top level, as a lone value has no key")]
+    UnsupportedTopLevel,
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::serializer::io)))]
+    #[error("Failed to write the output")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl ser::Error for SerializerError {
     fn custom<T: Display>(msg: T) -> Self {
         Self::Custom(msg.to_string())
     }
 }
+
+/// Everything that can go wrong while reading or applying a [`crate::patch::Patch`].
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "miette", derive(Diagnostic))]
+pub enum PatchError {
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::patch::syntax)))]
+    #[error("Expected `set key = value`, `delete key` or `append key`")]
+    Syntax {
+        #[cfg_attr(feature = "miette", label("This isn't an edit"))]
+        span: Range<usize>,
+    },
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::patch::missing)))]
+    #[error("There's nothing at `{path}`")]
+    Missing { path: String },
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::patch::node)))]
+    #[error("`{path}` is a value, so it has no children")]
+    ExpectedNode { path: String },
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::patch::sequence)))]
+    #[error("`{path}` isn't a sequence")]
+    ExpectedSequence { path: String },
+    #[cfg_attr(feature = "miette", diagnostic(code(divatree::patch::index)))]
+    #[error("`{path}` only has {length} elements")]
+    OutOfBounds { path: String, length: usize },
+}
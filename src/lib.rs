@@ -1,12 +1,25 @@
+pub mod a3da;
+pub mod bools;
+pub mod cst;
 pub mod de;
+pub mod diff;
 pub mod error;
+pub mod merge;
+pub mod patch;
 #[cfg(feature = "pyo3")]
 mod py_ffi;
+pub mod pv_db;
+pub mod radix;
 pub mod ser;
+pub mod sparse;
+pub mod value;
 
+pub use bools::BoolStyle;
 pub use de::*;
 pub use error::*;
 pub use ser::*;
+pub use sparse::Sparse;
+pub use value::Value;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,585 @@
+//! Stacking several files on top of each other, the way mod loaders stack every
+//! `mod_pv_db.txt` on top of the game's own `pv_db.txt`.
+//!
+//! Files are merged key by key, and sequences index by index. A key that more than one file
+//! sets to different values is a [`Conflict`], which [`Precedence`] settles. Sequences are
+//! renumbered from `0` afterwards, along with a `length` that matches.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_divatree::cst::Document;
+//! use serde_divatree::merge::{self, Precedence};
+//!
+//! let base = Document::parse("pv_001.bpm=120\npv_001.song_name=Foo\n");
+//! let module = Document::parse("pv_001.bpm=150\npv_002.bpm=90\n");
+//! let (merged, conflicts) = merge::merge_documents(
+//!     [("pv_db.txt", &base), ("mod_pv_db.txt", &module)],
+//!     Precedence::LastWins,
+//! )
+//! .unwrap();
+//! assert_eq!(
+//!     merged.to_string(),
+//!     "pv_001.bpm=150\npv_001.song_name=Foo\npv_002.bpm=90\n"
+//! );
+//! assert_eq!(
+//!     conflicts[0].to_string(),
+//!     "pv_001.bpm: mod_pv_db.txt:1 overrides pv_db.txt:1"
+//! );
+//! ```
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::ops::{Bound, Range};
+
+use crate::cst::{Document, Editor};
+use crate::de::KeyValue;
+use crate::error::SerializerError;
+use crate::serde::SEQ_ENDER;
+use crate::value::{index, Value};
+
+/// Which file's value is kept when several set the same key.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum Precedence {
+    /// Files later in the list override earlier ones, like mods loaded on top of the game.
+    #[default]
+    LastWins,
+    /// The first file to set a key keeps it.
+    FirstWins,
+}
+
+/// Where a value in a merge comes from.
+///
+/// Only [`merge_documents`] knows which line a value is on, as a [`Value`] doesn't keep its
+/// lines, so [`merge_values`] leaves [`Origin::line`] and [`Origin::span`] as `None`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Hash)]
+pub struct Origin {
+    /// The name the file was merged under
+    pub file: String,
+    /// The line it's on, counting from `1`, when merged by [`merge_documents`]
+    pub line: Option<usize>,
+    /// The span of that line in the file, when merged by [`merge_documents`]
+    pub span: Option<Range<usize>>,
+    /// The value itself, unless it's a whole node
+    pub value: Option<String>,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// A key set to different values by two files.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Conflict {
+    /// The key both files set, or the node above it one of them set to a value
+    pub path: String,
+    pub kept: Origin,
+    pub dropped: Origin,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} overrides {}", self.path, self.kept, self.dropped)
+    }
+}
+
+/// Merges `layers` in order, each along with the name of its file.
+///
+/// The [`Origin`] of every conflict only has its file and value, without a line or span. Use
+/// [`merge_documents`] to know where in the files they are.
+pub fn merge_values<'a, I>(layers: I, precedence: Precedence) -> (Value, Vec<Conflict>)
+where
+    I: IntoIterator<Item = (&'a str, &'a Value)>,
+{
+    let layers: Vec<_> = layers.into_iter().collect();
+    let mut merged = layers
+        .first()
+        .map(|(_, value)| (*value).clone())
+        .unwrap_or_default();
+    let mut merge = ValueMerge {
+        layers: &layers,
+        precedence,
+        current: 0,
+        conflicts: Vec::new(),
+    };
+    for (index, (_, value)) in layers.iter().enumerate().skip(1) {
+        merge.current = index;
+        merge.merge(&mut merged, value, &mut Vec::new());
+    }
+    resequence_value(&mut merged);
+    (merged, merge.conflicts)
+}
+
+/// Renumbers every sequence under `value` from `0`, and sets its `length` to match.
+fn resequence_value(value: &mut Value) {
+    match value {
+        Value::Map(map) => map.values_mut().for_each(resequence_value),
+        Value::Seq(seq) => {
+            seq.resequence();
+            seq.iter_mut()
+                .for_each(|(_, value)| resequence_value(value));
+        }
+        _ => {}
+    }
+}
+
+struct ValueMerge<'a, 'b> {
+    layers: &'b [(&'a str, &'a Value)],
+    precedence: Precedence,
+    /// The index of the layer being merged
+    current: usize,
+    conflicts: Vec<Conflict>,
+}
+
+impl ValueMerge<'_, '_> {
+    fn merge(&mut self, dst: &mut Value, src: &Value, path: &mut Vec<String>) {
+        match (dst, src) {
+            (Value::Map(dst), Value::Map(src)) => {
+                for (key, src) in src {
+                    path.push(key.clone());
+                    match dst.get_mut(key) {
+                        Some(dst) => self.merge(dst, src, path),
+                        None => {
+                            dst.insert(key.clone(), src.clone());
+                        }
+                    }
+                    path.pop();
+                }
+            }
+            (Value::Seq(dst), Value::Seq(src)) => {
+                for (index, src) in src.iter() {
+                    path.push(index.to_string());
+                    match dst.get_mut(index) {
+                        Some(dst) => self.merge(dst, src, path),
+                        None => {
+                            dst.insert(index, src.clone());
+                        }
+                    }
+                    path.pop();
+                }
+            }
+            (dst, src) if *dst == *src => {}
+            (dst, src) => {
+                let path = path.join(".");
+                let (file, _) = self.layers[self.current];
+                let new = Origin {
+                    file: file.to_string(),
+                    value: src.to_atom(),
+                    ..Origin::default()
+                };
+                let old = self.origin(&path);
+                self.conflicts.push(match self.precedence {
+                    Precedence::LastWins => Conflict {
+                        path,
+                        kept: new,
+                        dropped: old,
+                    },
+                    Precedence::FirstWins => Conflict {
+                        path,
+                        kept: old,
+                        dropped: new,
+                    },
+                });
+                if self.precedence == Precedence::LastWins {
+                    *dst = src.clone();
+                }
+            }
+        }
+    }
+
+    /// Where the value already merged at `path` comes from.
+    fn origin(&self, path: &str) -> Origin {
+        let layers = &self.layers[..self.current];
+        let find = |(file, value): &(&str, &Value)| {
+            value.get(path).map(|value| Origin {
+                file: file.to_string(),
+                value: value.to_atom(),
+                ..Origin::default()
+            })
+        };
+        let origin = match self.precedence {
+            Precedence::LastWins => layers.iter().rev().find_map(find),
+            Precedence::FirstWins => layers.iter().find_map(find),
+        };
+        origin.unwrap_or_default()
+    }
+}
+
+/// Merges `layers` in order, each along with the name of its file.
+///
+/// The first document is kept as it is, along with its comments and formatting, and the
+/// entries of the others are added to it. Fails if an entry has a key that can't be written
+/// back, like an empty one.
+pub fn merge_documents<'a, I>(
+    layers: I,
+    precedence: Precedence,
+) -> Result<(Document, Vec<Conflict>), SerializerError>
+where
+    I: IntoIterator<Item = (&'a str, &'a Document)>,
+{
+    let layers: Vec<_> = layers
+        .into_iter()
+        .map(|(file, doc)| Layer::new(file, doc))
+        .collect();
+    let mut merged = Editor::new(
+        layers
+            .first()
+            .map(|layer| layer.doc.clone())
+            .unwrap_or_default(),
+    );
+    let mut conflicts = Vec::new();
+    for (index, layer) in layers.iter().enumerate().skip(1) {
+        let lengths: BTreeSet<_> = sequences(layer.doc)
+            .into_values()
+            .filter_map(|x| x.ender)
+            .collect();
+        // The nodes of this layer that lost a conflict, to skip the rest of their entries
+        let mut dropped = BTreeSet::new();
+        for (line, entry) in layer
+            .doc
+            .lines()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| Some((i, x.as_entry()?)))
+        {
+            let key = entry.key();
+            if lengths.contains(key)
+                || parents(key)
+                    .chain(std::iter::once(key))
+                    .any(|x| dropped.contains(x))
+            {
+                continue;
+            }
+            // The node in the way of this entry: the key itself, a value above it or the
+            // entries under it
+            let taken = match merged.get(key) {
+                Some(value) if value == entry.value() => continue,
+                Some(_) => Some(key.to_string()),
+                None => parents(key)
+                    .find(|x| merged.get(x).is_some())
+                    .map(String::from)
+                    .or_else(|| merged.has_children(key).then(|| key.to_string())),
+            };
+            let path = match taken {
+                Some(path) => path,
+                None => {
+                    merged.set(key, entry.value())?;
+                    continue;
+                }
+            };
+            let new = Origin {
+                value: Some(entry.value().to_string()),
+                ..layer.origin(line)
+            };
+            let old = {
+                let layers = &layers[..index];
+                let origin = match precedence {
+                    Precedence::LastWins => layers.iter().rev().find_map(|x| x.first_under(&path)),
+                    Precedence::FirstWins => layers.iter().find_map(|x| x.first_under(&path)),
+                };
+                Origin {
+                    value: merged.get(&path).map(String::from),
+                    ..origin.unwrap_or_default()
+                }
+            };
+            match precedence {
+                Precedence::LastWins => {
+                    if path != key || merged.get(key).is_none() {
+                        merged.remove_all(&path);
+                    }
+                    merged.set(key, entry.value())?;
+                    conflicts.push(Conflict {
+                        path,
+                        kept: new,
+                        dropped: old,
+                    });
+                }
+                Precedence::FirstWins => {
+                    dropped.insert(path.clone());
+                    conflicts.push(Conflict {
+                        path,
+                        kept: old,
+                        dropped: new,
+                    });
+                }
+            }
+        }
+    }
+    let merged = resequence(merged.finish())?;
+    Ok((merged, conflicts))
+}
+
+/// A document being merged, along with where each of its keys first is.
+struct Layer<'a> {
+    file: &'a str,
+    doc: &'a Document,
+    /// The line of the first entry with each key
+    keys: BTreeMap<&'a str, usize>,
+    spans: Vec<Range<usize>>,
+}
+
+impl<'a> Layer<'a> {
+    fn new(file: &'a str, doc: &'a Document) -> Self {
+        let mut keys = BTreeMap::new();
+        for (line, entry) in doc
+            .lines()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| Some((i, x.as_entry()?)))
+        {
+            keys.entry(entry.key()).or_insert(line);
+        }
+        Self {
+            file,
+            doc,
+            keys,
+            spans: doc.spans(),
+        }
+    }
+
+    fn origin(&self, line: usize) -> Origin {
+        Origin {
+            file: self.file.to_string(),
+            line: Some(line + 1),
+            span: Some(self.spans[line].clone()),
+            value: None,
+        }
+    }
+
+    /// Where the first entry that's `path` or under it is.
+    fn first_under(&self, path: &str) -> Option<Origin> {
+        let prefix = format!("{}{}", path, KeyValue::PATH_DELIMITER);
+        let children = self
+            .keys
+            .range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, line)| *line);
+        let line = self
+            .keys
+            .get(path)
+            .copied()
+            .into_iter()
+            .chain(children)
+            .min()?;
+        Some(self.origin(line))
+    }
+}
+
+/// Every key above `key`, starting from the root.
+fn parents(key: &str) -> impl Iterator<Item = &str> {
+    key.match_indices(KeyValue::PATH_DELIMITER)
+        .map(move |(i, _)| &key[..i])
+}
+
+/// A node whose children are all indices, along with its `length`.
+struct Sequence {
+    /// Every index, sorted
+    indices: Vec<usize>,
+    /// The key of its `length` entry
+    ender: Option<String>,
+}
+
+fn join(parent: &str, child: &str) -> String {
+    if parent.is_empty() {
+        child.to_string()
+    } else {
+        format!("{}{}{}", parent, KeyValue::PATH_DELIMITER, child)
+    }
+}
+
+/// Every sequence in `doc`, by its key.
+fn sequences(doc: &Document) -> BTreeMap<String, Sequence> {
+    let is_ender = |x: &str| SEQ_ENDER.iter().any(|ender| x.eq_ignore_ascii_case(ender));
+    let mut children: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for entry in doc.entries() {
+        let key = entry.key();
+        let mut start = 0usize;
+        for segment in key.split(KeyValue::PATH_DELIMITER) {
+            let parent = &key[..start.saturating_sub(1)];
+            children.entry(parent).or_default().insert(segment);
+            start += segment.len() + 1;
+        }
+    }
+    children
+        .into_iter()
+        .filter(|(_, children)| {
+            children.iter().any(|x| index(x).is_some())
+                && children.iter().all(|x| index(x).is_some() || is_ender(x))
+        })
+        .map(|(parent, children)| {
+            let mut indices: Vec<_> = children.iter().filter_map(|x| index(x)).collect();
+            indices.sort_unstable();
+            let ender = children
+                .iter()
+                .find(|x| is_ender(x))
+                .map(|x| join(parent, x));
+            (parent.to_string(), Sequence { indices, ender })
+        })
+        .collect()
+}
+
+/// Renumbers every sequence from `0`, and sets its `length` to match.
+fn resequence(mut doc: Document) -> Result<Document, SerializerError> {
+    let sequences = sequences(&doc);
+    let renumber = |key: &str| {
+        let mut out = String::with_capacity(key.len());
+        let mut start = 0usize;
+        for segment in key.split(KeyValue::PATH_DELIMITER) {
+            let parent = &key[..start.saturating_sub(1)];
+            if start > 0 {
+                out.push(KeyValue::PATH_DELIMITER);
+            }
+            match (sequences.get(parent), index(segment)) {
+                (Some(seq), Some(index)) => {
+                    let rank = seq.indices.binary_search(&index).unwrap_or(index);
+                    out.push_str(&rank.to_string());
+                }
+                _ => out.push_str(segment),
+            }
+            start += segment.len() + 1;
+        }
+        out
+    };
+    doc.rename(|key| {
+        let renumbered = renumber(key);
+        (renumbered != key).then_some(renumbered)
+    });
+    let mut doc = Editor::new(doc);
+    for (parent, seq) in &sequences {
+        let ender = seq
+            .ender
+            .clone()
+            .unwrap_or_else(|| join(parent, SEQ_ENDER[0]));
+        doc.set(&renumber(&ender), &seq.indices.len().to_string())?;
+    }
+    Ok(doc.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Seq;
+
+    const BASE: &str = "# pv_db
+pv_001.bpm=120
+pv_001.difficulty.hard.0.level=PV_LV_07_0
+pv_001.difficulty.hard.1.level=PV_LV_08_0
+pv_001.difficulty.hard.length=2
+pv_001.song_name=Foo
+";
+
+    const MODULE: &str = "pv_001.bpm=150
+pv_001.difficulty.hard.1.level=PV_LV_08_5
+pv_001.difficulty.hard.5.level=PV_LV_09_0
+pv_001.difficulty.hard.length=6
+pv_001.song_name.en=Foo
+pv_002.bpm=90
+";
+
+    #[test]
+    fn merge_last_wins() {
+        let base = Document::parse(BASE);
+        let module = Document::parse(MODULE);
+        let (merged, conflicts) = merge_documents(
+            [("pv_db.txt", &base), ("mod_pv_db.txt", &module)],
+            Precedence::LastWins,
+        )
+        .unwrap();
+        let expected = "# pv_db
+pv_001.bpm=150
+pv_001.difficulty.hard.0.level=PV_LV_07_0
+pv_001.difficulty.hard.1.level=PV_LV_08_5
+pv_001.difficulty.hard.2.level=PV_LV_09_0
+pv_001.difficulty.hard.length=3
+pv_001.song_name.en=Foo
+pv_002.bpm=90
+";
+        assert_eq!(merged.to_string(), expected);
+        assert_eq!(conflicts.len(), 3);
+        assert_eq!(conflicts[0].path, "pv_001.bpm");
+        assert_eq!(conflicts[0].kept.value.as_deref(), Some("150"));
+        assert_eq!(conflicts[0].dropped.value.as_deref(), Some("120"));
+        assert_eq!(conflicts[0].dropped.line, Some(2));
+        assert_eq!(conflicts[0].dropped.span, Some(8..22));
+        assert_eq!(
+            conflicts[2].to_string(),
+            "pv_001.song_name: mod_pv_db.txt:5 overrides pv_db.txt:6"
+        );
+    }
+
+    #[test]
+    fn merge_invalid_key() {
+        let base = Document::parse(BASE);
+        let module = Document::parse("pv_002.bpm=90\n=5\n");
+        let result = merge_documents(
+            [("pv_db.txt", &base), ("mod_pv_db.txt", &module)],
+            Precedence::LastWins,
+        );
+        assert!(matches!(result, Err(SerializerError::InvalidKey(key)) if key.is_empty()));
+    }
+
+    #[test]
+    fn merge_first_wins() {
+        let base = Document::parse(BASE);
+        let module = Document::parse(MODULE);
+        let (merged, conflicts) = merge_documents(
+            [("pv_db.txt", &base), ("mod_pv_db.txt", &module)],
+            Precedence::FirstWins,
+        )
+        .unwrap();
+        let expected = "# pv_db
+pv_001.bpm=120
+pv_001.difficulty.hard.0.level=PV_LV_07_0
+pv_001.difficulty.hard.1.level=PV_LV_08_0
+pv_001.difficulty.hard.2.level=PV_LV_09_0
+pv_001.difficulty.hard.length=3
+pv_001.song_name=Foo
+pv_002.bpm=90
+";
+        assert_eq!(merged.to_string(), expected);
+        assert_eq!(conflicts.len(), 3);
+        assert_eq!(conflicts[1].kept.value.as_deref(), Some("PV_LV_08_0"));
+        assert_eq!(conflicts[2].kept.value.as_deref(), Some("Foo"));
+        assert_eq!(conflicts[2].dropped.line, Some(5));
+    }
+
+    #[test]
+    fn merge_value_layers() {
+        let base: Value = BASE.parse().unwrap();
+        let module: Value = MODULE.parse().unwrap();
+        let (merged, conflicts) = merge_values(
+            [("pv_db.txt", &base), ("mod_pv_db.txt", &module)],
+            Precedence::LastWins,
+        );
+        assert_eq!(merged["pv_001.bpm"].as_str(), Some("150"));
+        assert_eq!(
+            merged["pv_001.difficulty.hard"].as_seq().map(Seq::len),
+            Some(3)
+        );
+        assert_eq!(
+            merged["pv_001.difficulty.hard.2.level"].as_str(),
+            Some("PV_LV_09_0")
+        );
+        assert_eq!(merged["pv_002.bpm"].as_str(), Some("90"));
+        let paths: Vec<_> = conflicts.iter().map(|x| x.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "pv_001.bpm",
+                "pv_001.difficulty.hard.1.level",
+                "pv_001.song_name"
+            ]
+        );
+        assert_eq!(conflicts[0].dropped.file, "pv_db.txt");
+        assert_eq!(conflicts[0].dropped.line, None);
+        assert_eq!(conflicts[0].dropped.span, None);
+        assert_eq!(conflicts[0].dropped.value.as_deref(), Some("120"));
+        assert_eq!(conflicts[2].dropped.value, Some("Foo".into()));
+        assert_eq!(conflicts[2].kept.value, None);
+    }
+}
@@ -0,0 +1,460 @@
+//! Edits to a file, written as the key paths they change.
+//!
+//! ```text
+//! # Blank lines and comments are skipped
+//! set pv_123.bpm = 150
+//! delete pv_123.difficulty.easy.1
+//! append pv_123.difficulty.hard
+//!     level = PV_LV_07_5
+//!     script_file_name = rom/script/pv_123_hard.dsc
+//! append pv_123.lyric = La la la
+//! ```
+//!
+//! Sequences keep their indices in order: deleting an element moves every element after it
+//! down by one, and appending one adds it after the last. Their `length` grows and shrinks
+//! along with them.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_divatree::patch::Patch;
+//! use serde_divatree::Value;
+//!
+//! let mut pv_db: Value = "pv_123.bpm=120
+//! pv_123.difficulty.easy.0.level=PV_LV_02_0
+//! pv_123.difficulty.easy.1.level=PV_LV_03_0
+//! pv_123.difficulty.easy.2.level=PV_LV_04_0
+//! pv_123.difficulty.easy.length=3
+//! "
+//! .parse()
+//! .unwrap();
+//! let patch: Patch = "set pv_123.bpm = 150
+//! delete pv_123.difficulty.easy.1
+//! append pv_123.difficulty.hard
+//!     level = PV_LV_07_5
+//! "
+//! .parse()
+//! .unwrap();
+//! patch.apply(&mut pv_db).unwrap();
+//! assert_eq!(
+//!     pv_db.to_string(),
+//!     "pv_123.bpm=150
+//! pv_123.difficulty.easy.0.level=PV_LV_02_0
+//! pv_123.difficulty.easy.1.level=PV_LV_04_0
+//! pv_123.difficulty.easy.length=2
+//! pv_123.difficulty.hard.0.level=PV_LV_07_5
+//! pv_123.difficulty.hard.length=1
+//! "
+//! );
+//! ```
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::de::{KeyValue, Source};
+use crate::error::PatchError;
+use crate::value::{index, Seq, Value};
+
+/// A single edit of a [`Patch`].
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Edit {
+    /// Sets a value, adding every node above it that's missing.
+    Set { path: String, value: String },
+    /// Removes a node along with everything under it.
+    Delete { path: String },
+    /// Adds an element at the end of a sequence, which is created if it's missing.
+    ///
+    /// The element is a single atom, or a node with the children it's given.
+    Append { path: String, value: Value },
+}
+
+impl Edit {
+    /// The key path the edit changes.
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Set { path, .. } | Self::Delete { path } | Self::Append { path, .. } => path,
+        }
+    }
+
+    /// Applies the edit to `root`, which is left as it was if the edit fails.
+    pub fn apply(&self, root: &mut Value) -> Result<(), PatchError> {
+        match self {
+            Self::Set { path, value } => {
+                *node_mut(root, path, true)? = Value::atom(value);
+            }
+            Self::Delete { path } => {
+                let (parent, key) = match path.rsplit_once(KeyValue::PATH_DELIMITER) {
+                    Some((parent, key)) => (node_mut(root, parent, false)?, key),
+                    None => (root, path.as_str()),
+                };
+                let missing = || PatchError::Missing { path: path.clone() };
+                match parent {
+                    Value::Map(map) => {
+                        map.remove(key).ok_or_else(missing)?;
+                    }
+                    Value::Seq(seq) => {
+                        index(key)
+                            .and_then(|index| seq.remove(index))
+                            .ok_or_else(missing)?;
+                    }
+                    _ => return Err(missing()),
+                }
+            }
+            Self::Append { path, value } => {
+                let node = node_mut(root, path, true)?;
+                if matches!(node, Value::Map(map) if map.is_empty()) {
+                    *node = Value::Seq(Seq::new());
+                }
+                match node {
+                    Value::Seq(seq) => seq.push(value.clone()),
+                    _ => return Err(PatchError::ExpectedSequence { path: path.clone() }),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Set { path, value } => writeln!(f, "set {} = {}", path, value),
+            Self::Delete { path } => writeln!(f, "delete {}", path),
+            Self::Append { path, value } => match value.to_atom() {
+                Some(atom) => writeln!(f, "append {} = {}", path, atom),
+                None => {
+                    writeln!(f, "append {}", path)?;
+                    let fields = crate::to_string(value).map_err(|_| fmt::Error)?;
+                    for field in fields.lines().filter_map(KeyValue::new) {
+                        writeln!(f, "    {} = {}", field.key, field.value)?;
+                    }
+                    Ok(())
+                }
+            },
+        }
+    }
+}
+
+/// A list of edits, applied in order.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Hash)]
+pub struct Patch {
+    pub edits: Vec<Edit>,
+}
+
+impl Patch {
+    /// Reads a patch, one edit per line.
+    pub fn parse(s: &str) -> Result<Self, PatchError> {
+        let source = Source::Str(s);
+        let mut edits = Vec::new();
+        // The line of a block append that has no children yet
+        let mut childless = None;
+        for line in s.lines() {
+            let syntax = || PatchError::Syntax {
+                span: source.span(line),
+            };
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                childless = None;
+                // A child of the element appended last
+                let child = match edits.last_mut() {
+                    Some(Edit::Append { value, .. }) if value.to_atom().is_none() => value,
+                    _ => return Err(syntax()),
+                };
+                let (key, value) = line.split_once('=').ok_or_else(syntax)?;
+                let key = key.trim();
+                if key.is_empty() {
+                    return Err(syntax());
+                }
+                *node_mut(child, key, true).map_err(|_| syntax())? = Value::atom(value.trim());
+                continue;
+            }
+            if let Some(span) = childless.take() {
+                return Err(PatchError::Syntax { span });
+            }
+            let (command, rest) = line.split_once(char::is_whitespace).ok_or_else(syntax)?;
+            let (path, value) = match rest.split_once('=') {
+                Some((path, value)) => (path.trim(), Some(value.trim())),
+                None => (rest.trim(), None),
+            };
+            if path.is_empty() {
+                return Err(syntax());
+            }
+            let path = path.to_string();
+            let edit = match (command, value) {
+                ("set", Some(value)) => Edit::Set {
+                    path,
+                    value: value.to_string(),
+                },
+                ("delete", None) => Edit::Delete { path },
+                ("append", Some(value)) => Edit::Append {
+                    path,
+                    value: Value::atom(value),
+                },
+                ("append", None) => {
+                    childless = Some(source.span(line));
+                    Edit::Append {
+                        path,
+                        value: Value::Map(BTreeMap::new()),
+                    }
+                }
+                _ => return Err(syntax()),
+            };
+            edits.push(edit);
+        }
+        match childless {
+            Some(span) => Err(PatchError::Syntax { span }),
+            None => Ok(Self { edits }),
+        }
+    }
+
+    /// Applies every edit in order, leaving `root` as it was if any of them fails.
+    pub fn apply(&self, root: &mut Value) -> Result<(), PatchError> {
+        let mut patched = root.clone();
+        for edit in &self.edits {
+            edit.apply(&mut patched)?;
+        }
+        *root = patched;
+        Ok(())
+    }
+}
+
+impl FromStr for Patch {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for edit in &self.edits {
+            write!(f, "{}", edit)?;
+        }
+        Ok(())
+    }
+}
+
+/// Looks up the node at `path`, adding every node that's missing when `create` is set.
+///
+/// An index in a gap of a sequence or at its [`Seq::end`] adds an element there. Nothing is
+/// added unless the whole path can be.
+fn node_mut<'a>(
+    root: &'a mut Value,
+    path: &str,
+    create: bool,
+) -> Result<&'a mut Value, PatchError> {
+    let segments: Vec<_> = path.split(KeyValue::PATH_DELIMITER).collect();
+    if create {
+        check(root, &segments)?;
+    }
+    let mut node = root;
+    for (depth, key) in segments.iter().enumerate() {
+        let path = || segments[..=depth].join(".");
+        let parent = || segments[..depth].join(".");
+        // A missing node is a sequence when it's indexed next
+        let empty = || match segments.get(depth + 1) {
+            Some(next) if index(next).is_some() => Value::Seq(Seq::new()),
+            _ => Value::Map(BTreeMap::new()),
+        };
+        node = match node {
+            Value::Map(map) => {
+                if create && !map.contains_key(*key) {
+                    map.insert(key.to_string(), empty());
+                }
+                map.get_mut(*key)
+                    .ok_or_else(|| PatchError::Missing { path: path() })?
+            }
+            Value::Seq(seq) => {
+                let end = seq.end();
+                let index = index(key).ok_or_else(|| PatchError::Missing { path: path() })?;
+                if create && index <= end && seq.get(index).is_none() {
+                    seq.insert(index, empty());
+                }
+                match seq.get_mut(index) {
+                    Some(child) => child,
+                    None if index < end => return Err(PatchError::Missing { path: path() }),
+                    None => {
+                        return Err(PatchError::OutOfBounds {
+                            path: parent(),
+                            length: end,
+                        })
+                    }
+                }
+            }
+            _ => return Err(PatchError::ExpectedNode { path: parent() }),
+        };
+    }
+    Ok(node)
+}
+
+/// Fails with the error [`node_mut`] would, without adding anything.
+fn check(root: &Value, segments: &[&str]) -> Result<(), PatchError> {
+    let mut node = root;
+    for (depth, key) in segments.iter().enumerate() {
+        let child = match node {
+            Value::Map(map) => map.get(*key),
+            Value::Seq(seq) => {
+                let index = index(key).ok_or_else(|| PatchError::Missing {
+                    path: segments[..=depth].join("."),
+                })?;
+                if index > seq.end() {
+                    return Err(PatchError::OutOfBounds {
+                        path: segments[..depth].join("."),
+                        length: seq.end(),
+                    });
+                }
+                seq.get(index)
+            }
+            _ => {
+                return Err(PatchError::ExpectedNode {
+                    path: segments[..depth].join("."),
+                })
+            }
+        };
+        match child {
+            Some(child) => node = child,
+            None => {
+                // Everything below is added, and a new sequence can only start at 0
+                let added = segments[depth..].windows(2).enumerate();
+                for (offset, pair) in added {
+                    if index(pair[1]).is_some_and(|index| index != 0) {
+                        return Err(PatchError::OutOfBounds {
+                            path: segments[..=depth + offset].join("."),
+                            length: 0,
+                        });
+                    }
+                }
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "pv_001.bpm=120
+pv_001.difficulty.easy.0.level=PV_LV_02_0
+pv_001.difficulty.easy.1.level=PV_LV_03_0
+pv_001.difficulty.easy.length=2
+pv_001.song_name=Foo
+";
+
+    #[test]
+    fn apply_patch() {
+        let mut value: Value = INPUT.parse().unwrap();
+        let patch: Patch = "# update pv_001
+set pv_001.bpm=150
+delete pv_001.difficulty.easy.0
+append pv_001.difficulty.easy
+  level = PV_LV_05_0
+  attribute.original = 1
+
+append pv_001.lyric = La
+append pv_001.lyric = (1, 2)
+set pv_001.difficulty.hard.0.level = PV_LV_07_0
+delete pv_001.song_name
+"
+        .parse()
+        .unwrap();
+        assert_eq!(patch.edits.len(), 7);
+        patch.apply(&mut value).unwrap();
+        let expected = "pv_001.bpm=150
+pv_001.difficulty.easy.0.level=PV_LV_03_0
+pv_001.difficulty.easy.1.attribute.original=1
+pv_001.difficulty.easy.1.level=PV_LV_05_0
+pv_001.difficulty.easy.length=2
+pv_001.difficulty.hard.0.level=PV_LV_07_0
+pv_001.difficulty.hard.length=1
+pv_001.lyric.0=La
+pv_001.lyric.1=(1, 2)
+pv_001.lyric.length=2
+";
+        assert_eq!(value.to_string(), expected);
+        assert_eq!(patch.to_string().parse::<Patch>(), Ok(patch));
+    }
+
+    #[test]
+    fn apply_patch_sparse() {
+        let input = "lyric.0=La
+lyric.3=Li
+lyric.5=Lu
+lyric.num=7
+";
+        let mut value: Value = input.parse().unwrap();
+        let patch: Patch = "delete lyric.3
+append lyric = Le
+set lyric.1 = Lo
+"
+        .parse()
+        .unwrap();
+        patch.apply(&mut value).unwrap();
+        let expected = "lyric.0=La
+lyric.1=Lo
+lyric.4=Lu
+lyric.6=Le
+lyric.num=7
+";
+        assert_eq!(value.to_string(), expected);
+    }
+
+    #[test]
+    fn patch_errors() {
+        let err = "set pv_001.bpm = 1\nmove pv_001.bpm".parse::<Patch>();
+        assert_eq!(err, Err(PatchError::Syntax { span: 19..34 }));
+        assert!("  level = 1".parse::<Patch>().is_err());
+        assert!("delete pv_001.bpm = 1".parse::<Patch>().is_err());
+        assert!("set pv_001.bpm".parse::<Patch>().is_err());
+        let err = "append pv_001.lyric\n# no fields\nset pv_001.bpm = 1".parse::<Patch>();
+        assert_eq!(err, Err(PatchError::Syntax { span: 0..19 }));
+        assert!("append pv_001.lyric\n".parse::<Patch>().is_err());
+
+        let mut value: Value = INPUT.parse().unwrap();
+        let apply = |value: &mut Value, patch: &str| patch.parse::<Patch>().unwrap().apply(value);
+        assert_eq!(
+            apply(&mut value, "set pv_001.bpm = 1\ndelete pv_002"),
+            Err(PatchError::Missing {
+                path: "pv_002".into()
+            })
+        );
+        assert_eq!(value.to_string(), INPUT);
+        assert_eq!(
+            apply(&mut value, "set pv_001.difficulty.easy.3.level = 1"),
+            Err(PatchError::OutOfBounds {
+                path: "pv_001.difficulty.easy".into(),
+                length: 2,
+            })
+        );
+        assert_eq!(
+            apply(&mut value, "set pv_001.bpm.fast = 1"),
+            Err(PatchError::ExpectedNode {
+                path: "pv_001.bpm".into()
+            })
+        );
+        assert_eq!(
+            apply(&mut value, "append pv_001.song_name = Bar"),
+            Err(PatchError::ExpectedSequence {
+                path: "pv_001.song_name".into()
+            })
+        );
+
+        // A single edit adds nothing when its path can't be made
+        let edit = Edit::Set {
+            path: "pv_002.lyric.1".into(),
+            value: "La".into(),
+        };
+        assert_eq!(
+            edit.apply(&mut value),
+            Err(PatchError::OutOfBounds {
+                path: "pv_002.lyric".into(),
+                length: 0,
+            })
+        );
+        assert_eq!(value.to_string(), INPUT);
+    }
+}
@@ -0,0 +1,399 @@
+//! Typed structs for `pv_db.txt`, the list of every song in the game.
+//!
+//! Every field is optional, and a field that isn't known here is kept in the `extra` map of
+//! its struct as a [`Value`], so data from newer games is written back the way it was read.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_divatree::pv_db::PvDb;
+//!
+//! let input = "pv_001.bpm=120
+//! pv_001.difficulty.hard.0.level=PV_LV_07_5
+//! pv_001.difficulty.hard.0.script_format=0x14012316
+//! pv_001.difficulty.hard.length=1
+//! pv_001.song_name=Foo
+//! pv_001.unknown_field=Bar
+//! ";
+//! let pv_db: PvDb = serde_divatree::from_str(input).unwrap();
+//! let pv = &pv_db.pvs[&1];
+//! assert_eq!(pv.bpm, Some(120));
+//! let hard = &pv.difficulty.as_ref().unwrap().hard.as_ref().unwrap()[0];
+//! assert_eq!(hard.script_format.unwrap().value, 0x14012316);
+//! assert_eq!(pv.extra["unknown_field"].as_str(), Some("Bar"));
+//! assert_eq!(serde_divatree::to_string(&pv_db).unwrap(), input);
+//! ```
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_derive::Serialize;
+
+use crate::radix::Int;
+use crate::value::Value;
+
+/// The key of a field, which is its name unless it's renamed.
+macro_rules! key {
+    ($field:ident) => {
+        stringify!($field)
+    };
+    ($field:ident, $rename:literal) => {
+        $rename
+    };
+}
+
+/// Declares a struct of optional fields that keeps every other field in `extra`.
+///
+/// `#[serde(flatten)]` can't be used to read `extra`, since it buffers every value and
+/// guesses its type first, which turns a version like `1.00` into `1.0`.
+macro_rules! open_struct {
+    (
+        $(#[doc = $sdoc:literal])*
+        pub struct $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $(#[serde(rename = $rename:literal)])?
+                pub $field:ident: $ty:ty,
+            )*
+        }
+    ) => {
+        $(#[doc = $sdoc])*
+        #[derive(Debug, Default, Clone, PartialEq, Serialize)]
+        pub struct $name {
+            $(
+                $(#[doc = $doc])*
+                $(#[serde(rename = $rename)])?
+                pub $field: Option<$ty>,
+            )*
+            /// Every field that isn't known, as it was written
+            #[serde(flatten)]
+            pub extra: BTreeMap<String, Value>,
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                struct FieldVisitor;
+
+                impl<'de> Visitor<'de> for FieldVisitor {
+                    type Value = $name;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str(concat!("struct ", stringify!($name)))
+                    }
+
+                    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                    where
+                        A: MapAccess<'de>,
+                    {
+                        let mut val = $name::default();
+                        while let Some(key) = map.next_key::<String>()? {
+                            match key.as_str() {
+                                $(key!($field $(, $rename)?) => val.$field = Some(map.next_value()?),)*
+                                _ => {
+                                    let value = map.next_value()?;
+                                    val.extra.insert(key, value);
+                                }
+                            }
+                        }
+                        Ok(val)
+                    }
+                }
+
+                deserializer.deserialize_map(FieldVisitor)
+            }
+        }
+    };
+}
+
+/// A whole `pv_db.txt`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PvDb {
+    /// Every PV, by the number in its `pv_001` key
+    pub pvs: BTreeMap<u32, PvEntry>,
+    /// Every key that isn't a PV, as it was written
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// The key of the PV numbered `id`, like `pv_001`.
+pub fn pv_key(id: u32) -> String {
+    format!("pv_{:03}", id)
+}
+
+/// The number of the PV with `key`, if it's written the way [`pv_key`] writes it.
+pub fn pv_id(key: &str) -> Option<u32> {
+    let id = key.strip_prefix("pv_")?.parse().ok()?;
+    (pv_key(id) == key).then_some(id)
+}
+
+impl Serialize for PvDb {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.pvs.len() + self.extra.len()))?;
+        for (id, pv) in &self.pvs {
+            map.serialize_entry(&pv_key(*id), pv)?;
+        }
+        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PvDb {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(PvDbVisitor)
+    }
+}
+
+struct PvDbVisitor;
+
+impl<'de> Visitor<'de> for PvDbVisitor {
+    type Value = PvDb;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a pv_db")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut pv_db = PvDb::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match pv_id(&key) {
+                Some(id) => {
+                    pv_db.pvs.insert(id, map.next_value()?);
+                }
+                None => {
+                    pv_db.extra.insert(key, map.next_value()?);
+                }
+            }
+        }
+        Ok(pv_db)
+    }
+}
+
+open_struct! {
+    /// A single song, like `pv_001`.
+    pub struct PvEntry {
+        pub another_song: Vec<AnotherSong>,
+        pub bpm: u32,
+        pub chainslide_failure_name: String,
+        pub chainslide_first_name: String,
+        pub chainslide_sub_name: String,
+        pub chainslide_success_name: String,
+        /// The effects played on the characters
+        pub chreff: Vec<Chreff>,
+        /// The release date, like `20090702`
+        pub date: u32,
+        pub difficulty: Difficulties,
+        pub edit: u32,
+        pub hidden_timing: f32,
+        pub high_speed_rate: f32,
+        /// The lyrics by the number of each line, written as it was read, like `001`
+        pub lyric: BTreeMap<String, String>,
+        pub lyric_en: BTreeMap<String, String>,
+        /// The motions of the characters, in the order of `performer`
+        pub motion: Vec<String>,
+        pub movie_file_name: String,
+        pub movie_list: Vec<Movie>,
+        pub movie_surface: String,
+        pub performer: Vec<Performer>,
+        /// The part of the song played in the song list
+        pub sabi: Sabi,
+        pub se_name: String,
+        pub slide_name: String,
+        pub slidertouch_name: String,
+        pub song_file_name: String,
+        pub song_name: String,
+        pub song_name_en: String,
+        pub song_name_reading: String,
+        pub song_name_reading_en: String,
+        pub songinfo: SongInfo,
+        pub songinfo_en: SongInfo,
+        pub sudden_timing: f32,
+    }
+}
+
+open_struct! {
+    /// The charts of every difficulty, where each one can have an extra edition.
+    pub struct Difficulties {
+        pub easy: Vec<Difficulty>,
+        pub encore: Vec<Difficulty>,
+        pub extreme: Vec<Difficulty>,
+        pub hard: Vec<Difficulty>,
+        pub normal: Vec<Difficulty>,
+    }
+}
+
+open_struct! {
+    /// A single chart.
+    pub struct Difficulty {
+        /// `0` for the chart itself and `1` for its extra edition
+        pub edition: u32,
+        /// The star rating, like `PV_LV_07_5`
+        pub level: String,
+        pub level_sort_index: u32,
+        pub script_file_name: String,
+        /// The version of the script, usually written in hexadecimal
+        pub script_format: Int<u32>,
+        pub version: u32,
+    }
+}
+
+open_struct! {
+    /// Another version of the song, like one sung by another character.
+    pub struct AnotherSong {
+        pub name: String,
+        pub name_en: String,
+        pub song_file_name: String,
+        pub vocal_chara_num: String,
+        pub vocal_disp_name: String,
+        pub vocal_disp_name_en: String,
+    }
+}
+
+open_struct! {
+    pub struct Chreff {
+        pub data: Vec<ChreffData>,
+        pub id: u32,
+        pub name: String,
+    }
+}
+
+open_struct! {
+    pub struct ChreffData {
+        pub name: String,
+        #[serde(rename = "type")]
+        pub ty: String,
+    }
+}
+
+open_struct! {
+    pub struct Movie {
+        pub name: String,
+    }
+}
+
+open_struct! {
+    /// A character in the PV.
+    pub struct Performer {
+        /// The character, like `MIK`
+        pub chara: String,
+        /// The module they wear, unless the player picks one
+        pub pv_costume: u32,
+        pub size: u32,
+        /// What they do, like `VOCAL`
+        #[serde(rename = "type")]
+        pub ty: String,
+    }
+}
+
+open_struct! {
+    pub struct Sabi {
+        pub play_time: f32,
+        pub start_time: f32,
+    }
+}
+
+open_struct! {
+    /// The credits of the song.
+    pub struct SongInfo {
+        pub arranger: String,
+        pub guitar_player: String,
+        pub illustrator: String,
+        pub lyrics: String,
+        pub manipulator: String,
+        pub music: String,
+        pub pv_editor: String,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radix::Radix;
+
+    #[test]
+    fn round_trip() {
+        let input = "pv_001.bpm=120
+pv_001.date=20090702
+pv_001.difficulty.easy.0.edition=0
+pv_001.difficulty.easy.0.level=PV_LV_03_0
+pv_001.difficulty.easy.0.script_file_name=rom/script/pv_001_easy.dsc
+pv_001.difficulty.easy.0.script_format=0x14050921
+pv_001.difficulty.easy.0.version=1
+pv_001.difficulty.easy.length=1
+pv_001.difficulty.encore.length=0
+pv_001.difficulty.extreme.0.attribute.original=1
+pv_001.difficulty.extreme.0.edition=0
+pv_001.difficulty.extreme.0.level=PV_LV_08_0
+pv_001.difficulty.extreme.1.edition=1
+pv_001.difficulty.extreme.1.level=PV_LV_09_0
+pv_001.difficulty.extreme.length=2
+pv_001.disp2d.set_name=PV001
+pv_001.lyric.0=Hello
+pv_001.lyric.2=World
+pv_001.lyric.length=3
+pv_001.performer.0.chara=MIK
+pv_001.performer.0.type=VOCAL
+pv_001.performer.length=1
+pv_001.song_name=39
+pv_001.song_name_en=Foo
+pv_001.songinfo.music=Bar
+pv_1000.bpm=150
+pv_list.0=pv_001
+pv_list.length=1
+";
+        let pv_db: PvDb = crate::from_str(input).unwrap();
+        let pv = &pv_db.pvs[&1];
+        assert_eq!(pv.song_name.as_deref(), Some("39"));
+        assert_eq!(pv.date, Some(20090702));
+        let difficulty = pv.difficulty.as_ref().unwrap();
+        let easy = &difficulty.easy.as_ref().unwrap()[0];
+        assert_eq!(easy.script_format.unwrap().radix, Radix::Hexadecimal);
+        assert_eq!(difficulty.encore, Some(vec![]));
+        let extreme = difficulty.extreme.as_ref().unwrap();
+        assert_eq!(extreme[1].edition, Some(1));
+        assert!(extreme[0].extra.contains_key("attribute"));
+        assert_eq!(pv.lyric.as_ref().unwrap()["2"], "World");
+        assert_eq!(
+            pv.performer.as_ref().unwrap()[0].ty.as_deref(),
+            Some("VOCAL")
+        );
+        assert_eq!(pv.extra["disp2d"]["set_name"].as_str(), Some("PV001"));
+        assert_eq!(pv_db.pvs[&1000].bpm, Some(150));
+        assert!(pv_db.extra.contains_key("pv_list"));
+        assert_eq!(crate::to_string(&pv_db).unwrap(), input);
+    }
+
+    #[test]
+    fn round_trip_padded_lyrics() {
+        let input = "pv_001.lyric.001=Hello
+pv_001.lyric.002=World
+pv_001.lyric_en.001=Hello
+";
+        let pv_db: PvDb = crate::from_str(input).unwrap();
+        let lyric = pv_db.pvs[&1].lyric.as_ref().unwrap();
+        assert_eq!(lyric["002"], "World");
+        assert_eq!(crate::to_string(&pv_db).unwrap(), input);
+    }
+
+    #[test]
+    fn read_pv_id() {
+        assert_eq!(pv_id("pv_001"), Some(1));
+        assert_eq!(pv_id("pv_1000"), Some(1000));
+        assert_eq!(pv_id("pv_01"), None);
+        assert_eq!(pv_id("pv_list"), None);
+        assert_eq!(pv_key(42), "pv_042");
+    }
+}
@@ -1,11 +1,20 @@
 use pyo3::prelude::*;
-use pythonize::{Depythonizer, Pythonizer};
+use pyo3::types::{PyBool, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+use pythonize::Pythonizer;
+use serde::ser::{Error, Serialize, SerializeMap, SerializeSeq, SerializeTuple, Serializer};
 use serde_path_to_error::{Deserializer, Track};
 
+use crate::de::{Inference, Options, Source};
+use crate::error::DeserializerError;
 use crate::serde::Parser;
 
 /// Reads the object in `s` from its `CanonicalPath` form.
 ///
+/// Values are guessed as tuples, integers, floats, booleans and strings. The keyword arguments
+/// turn each guess off, with `strings_only` turning off all of them, and `leading_zeros` keeps
+/// values like `007` as strings. Integers with a `0x`, `0o` or `0b` prefix stay strings too,
+/// unless `radix` is set, which reads them as numbers that are written back in decimal.
+///
 /// # Example
 ///
 /// ```py
@@ -21,27 +30,127 @@ use crate::serde::Parser;
 /// 'script_file_name': 'rom/script/pv_999_normal.dsc',
 /// 'script_format': '0x14012316',
 /// 'version': 0}
+///
+/// >>> divatree.read("id=007\nversion=1.00\n", floats=False, leading_zeros=True)
+/// {'id': '007', 'version': '1.00'}
+///
+/// >>> divatree.read("script_format=0x14012316\n", radix=True)
+/// {'script_format': 335618838}
 /// ```
-#[pyfunction]
-fn read(py: Python, s: String) -> eyre::Result<PyObject> {
-    let mut iter = s
+#[pyfunction(
+    s,
+    "*",
+    strings_only = "false",
+    tuples = "true",
+    integers = "true",
+    floats = "true",
+    bools = "true",
+    leading_zeros = "false",
+    radix = "false"
+)]
+#[allow(clippy::too_many_arguments)]
+fn read(
+    py: Python,
+    s: String,
+    strings_only: bool,
+    tuples: bool,
+    integers: bool,
+    floats: bool,
+    bools: bool,
+    leading_zeros: bool,
+    radix: bool,
+) -> eyre::Result<PyObject> {
+    let inference = if strings_only {
+        Inference::strings()
+    } else {
+        Inference::default()
+            .with_tuples(tuples)
+            .with_integers(integers)
+            .with_floats(floats)
+            .with_bools(bools)
+    };
+    let inference = inference
+        .with_leading_zeros(leading_zeros)
+        .with_radix(radix);
+    let options = Options::default().with_inference(inference);
+    let iter = s
         .lines()
         .filter(|x| !x.trim().is_empty())
         .filter(|x| !x.starts_with('#'));
-    let mut lex = Parser::new(iter);
+    let mut lex = Parser::with_options(iter, options).with_source(Source::Str(&s));
     let mut track = Track::new();
-    let mut deser = Deserializer::new(&mut lex, &mut track);
+    let deser = Deserializer::new(&mut lex, &mut track);
     let topy = Pythonizer::new(py);
-    let obj = serde_transcode::transcode(deser, topy);
-    dbg!(track.path().to_string());
-    Ok(obj?)
+    let obj = serde_transcode::transcode(deser, topy).map_err(|err| {
+        // The error doesn't know the key it was read from yet, so it's given it here
+        <DeserializerError as serde::de::Error>::custom(err).with_path(&track.path())
+    })?;
+    Ok(obj)
 }
 
+/// Writes `obj` in its `CanonicalPath` form.
+///
+/// Lists are written as indexed sequences with a trailing `length`, while tuples are written
+/// as `(a, b, c)` atoms, the same way `read` returns them.
+///
+/// # Example
+///
+/// ```py
+/// >>> import divatree
+///
+/// >>> divatree.write({"pv_999": {"bpm": 150, "movie_list": ["pv_999.mp4"]}})
+/// 'pv_999.bpm=150\npv_999.movie_list.0=pv_999.mp4\npv_999.movie_list.length=1\n'
+/// ```
 #[pyfunction]
-fn write<'de>(obj: &'de PyAny) -> PyResult<String> {
-    let mut frompy = Depythonizer::from_object(obj);
-    todo!()
-    // serde_transcode::transcode(&mut frompy, topy).map_err(Into::into)
+fn write(obj: &PyAny) -> eyre::Result<String> {
+    Ok(crate::to_string(&PyValue(obj))?)
+}
+
+/// Serializes a Python object directly.
+///
+/// `Depythonizer` can't be transcoded here, as it reads both lists and tuples as sequences.
+struct PyValue<'py>(&'py PyAny);
+
+impl Serialize for PyValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let obj = self.0;
+        if let Ok(dict) = obj.downcast::<PyDict>() {
+            let mut map = serializer.serialize_map(Some(dict.len()))?;
+            for (key, value) in dict.iter() {
+                map.serialize_entry(&PyValue(key), &PyValue(value))?;
+            }
+            map.end()
+        } else if let Ok(list) = obj.downcast::<PyList>() {
+            let mut seq = serializer.serialize_seq(Some(list.len()))?;
+            for value in list.iter() {
+                seq.serialize_element(&PyValue(value))?;
+            }
+            seq.end()
+        } else if let Ok(tuple) = obj.downcast::<PyTuple>() {
+            let mut tup = serializer.serialize_tuple(tuple.len())?;
+            for value in tuple.iter() {
+                tup.serialize_element(&PyValue(value))?;
+            }
+            tup.end()
+        } else if let Ok(bool) = obj.downcast::<PyBool>() {
+            // `bool` is a subclass of `int`, so it has to be checked first
+            serializer.serialize_bool(bool.is_true())
+        } else if obj.downcast::<PyLong>().is_ok() {
+            match obj.extract::<i64>() {
+                Ok(int) => serializer.serialize_i64(int),
+                Err(_) => serializer.serialize_u64(obj.extract().map_err(S::Error::custom)?),
+            }
+        } else if let Ok(float) = obj.downcast::<PyFloat>() {
+            serializer.serialize_f64(float.value())
+        } else if let Ok(string) = obj.downcast::<PyString>() {
+            serializer.serialize_str(string.to_str().map_err(S::Error::custom)?)
+        } else if obj.is_none() {
+            serializer.serialize_none()
+        } else {
+            let ty = obj.get_type().name().unwrap_or("object");
+            Err(S::Error::custom(format!("cannot write a `{}`", ty)))
+        }
+    }
 }
 
 /// Read and write files using SEGA's flavor of the `CanonicalProperties` format.
@@ -0,0 +1,231 @@
+//! Integers written with a `0x`, `0o` or `0b` prefix.
+//!
+//! Every integer is read in the radix of its prefix, so `script_format=0x14012316` can be read
+//! into a `u32`. Writing it back gives `335618838` though, so [`Int`] keeps the radix along
+//! with the value for fields that should be written the way they were read.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_derive::{Deserialize, Serialize};
+//! use serde_divatree::radix::{Int, Radix};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Difficulty {
+//!     script_format: Int<u32>,
+//!     version: Int<u32>,
+//! }
+//!
+//! let input = "script_format=0x14012316
+//! version=1
+//! ";
+//! let difficulty: Difficulty = serde_divatree::from_str(input).unwrap();
+//! assert_eq!(difficulty.script_format.value, 0x14012316);
+//! assert_eq!(difficulty.script_format.radix, Radix::Hexadecimal);
+//! assert_eq!(serde_divatree::to_string(&difficulty).unwrap(), input);
+//! ```
+use std::fmt::{self, Display};
+use std::marker::PhantomData;
+use std::num::ParseIntError;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// The base an integer is written in, along with the prefix that marks it.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum Radix {
+    /// `0b101`
+    Binary,
+    /// `0o17`
+    Octal,
+    /// `42`, without a prefix
+    #[default]
+    Decimal,
+    /// `0x2a`
+    Hexadecimal,
+}
+
+impl Radix {
+    /// Finds the radix of `s` from its prefix, ignoring its sign.
+    pub fn of(s: &str) -> Self {
+        let s = s.strip_prefix(['+', '-']).unwrap_or(s);
+        match s.get(..2) {
+            Some("0b" | "0B") => Self::Binary,
+            Some("0o" | "0O") => Self::Octal,
+            Some("0x" | "0X") => Self::Hexadecimal,
+            _ => Self::Decimal,
+        }
+    }
+
+    pub fn base(self) -> u32 {
+        match self {
+            Self::Binary => 2,
+            Self::Octal => 8,
+            Self::Decimal => 10,
+            Self::Hexadecimal => 16,
+        }
+    }
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Self::Binary => "0b",
+            Self::Octal => "0o",
+            Self::Decimal => "",
+            Self::Hexadecimal => "0x",
+        }
+    }
+}
+
+/// The integer types that can be read in any [`Radix`].
+pub trait Integer: Sized {
+    fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError>;
+}
+
+macro_rules! integer {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Integer for $ty {
+                fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError> {
+                    <$ty>::from_str_radix(src, radix)
+                }
+            }
+        )*
+    };
+}
+
+integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Parses `s` in the radix of its prefix, returning the radix along with the value.
+pub fn parse<T: Integer>(s: &str) -> Result<(T, Radix), ParseIntError> {
+    let radix = Radix::of(s);
+    if radix == Radix::Decimal {
+        return T::from_str_radix(s, 10).map(|x| (x, radix));
+    }
+    let (negative, rest) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let digits = &rest[radix.prefix().len()..];
+    if digits.starts_with(['+', '-']) {
+        // A sign after the prefix, which isn't a number in any radix
+        return T::from_str_radix(s, 10).map(|x| (x, Radix::Decimal));
+    }
+    let value = if negative {
+        T::from_str_radix(&format!("-{}", digits), radix.base())
+    } else {
+        T::from_str_radix(digits, radix.base())
+    };
+    value.map(|x| (x, radix))
+}
+
+/// An integer that's written back in the same [`Radix`] it was read in.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub struct Int<T> {
+    pub value: T,
+    pub radix: Radix,
+}
+
+impl<T> Int<T> {
+    pub fn new(value: T, radix: Radix) -> Self {
+        Self { value, radix }
+    }
+}
+
+impl<T> From<T> for Int<T> {
+    fn from(value: T) -> Self {
+        Self::new(value, Radix::Decimal)
+    }
+}
+
+impl<T: Copy + Into<i128>> Display for Int<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value: i128 = self.value.into();
+        let sign = if value < 0 { "-" } else { "" };
+        let prefix = self.radix.prefix();
+        let value = value.unsigned_abs();
+        match self.radix {
+            Radix::Binary => write!(f, "{}{}{:b}", sign, prefix, value),
+            Radix::Octal => write!(f, "{}{}{:o}", sign, prefix, value),
+            Radix::Decimal => write!(f, "{}{}", sign, value),
+            Radix::Hexadecimal => write!(f, "{}{}{:x}", sign, prefix, value),
+        }
+    }
+}
+
+impl<T> Serialize for Int<T>
+where
+    T: Copy + Into<i128> + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.radix {
+            Radix::Decimal => self.value.serialize(serializer),
+            _ => serializer.collect_str(self),
+        }
+    }
+}
+
+impl<'de, T: Integer> Deserialize<'de> for Int<T> {
+    /// Reads the integer as text, so its prefix is still there.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(IntVisitor(PhantomData))
+    }
+}
+
+struct IntVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Integer> Visitor<'de> for IntVisitor<T> {
+    type Value = Int<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an integer")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        let (value, radix) = parse(v).map_err(E::custom)?;
+        Ok(Int::new(value, radix))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        self.visit_str(&v.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_radix() {
+        assert_eq!(parse("0x14012316"), Ok((0x14012316u32, Radix::Hexadecimal)));
+        assert_eq!(parse("0XfF"), Ok((255u8, Radix::Hexadecimal)));
+        assert_eq!(parse("-0o17"), Ok((-15i8, Radix::Octal)));
+        assert_eq!(parse("+0b101"), Ok((5u8, Radix::Binary)));
+        assert_eq!(parse("-128"), Ok((-128i8, Radix::Decimal)));
+        assert_eq!(parse("0"), Ok((0u8, Radix::Decimal)));
+        assert!(parse::<u8>("0x").is_err());
+        assert!(parse::<i8>("0x-1").is_err());
+        assert!(parse::<u8>("0x100").is_err());
+        assert!(parse::<u8>("0b2").is_err());
+    }
+
+    #[test]
+    fn write_radix() {
+        assert_eq!(
+            Int::new(0x14012316u32, Radix::Hexadecimal).to_string(),
+            "0x14012316"
+        );
+        assert_eq!(Int::new(-15i8, Radix::Octal).to_string(), "-0o17");
+        assert_eq!(Int::new(i64::MIN, Radix::Binary).to_string().len(), 67);
+        assert_eq!(Int::from(42u64).to_string(), "42");
+    }
+}
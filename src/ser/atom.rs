@@ -0,0 +1,450 @@
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::error::SerializerError;
+
+/// Renders a single value into the text that sits on the right of `=`.
+pub fn to_string<T>(value: &T) -> Result<String, SerializerError>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(AtomSerializer)
+}
+
+/// Checks that `v` is read back as it is from the right of `=`, which splits lines on every
+/// `=` and trims whitespace.
+pub(crate) fn check_str(v: &str) -> Result<(), SerializerError> {
+    if v.contains('\n') {
+        return Err(SerializerError::Unsupported("multiline string"));
+    }
+    if v.contains('=') || v.trim() != v {
+        return Err(SerializerError::InvalidValue(v.to_string()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AtomSerializer;
+
+impl AtomSerializer {
+    const TUPLE_START: char = '(';
+    const TUPLE_END: char = ')';
+    const TUPLE_DELIMITER: &str = ", ";
+}
+
+impl ser::Serializer for AtomSerializer {
+    type Ok = String;
+    type Error = SerializerError;
+
+    type SerializeSeq = Impossible<String, SerializerError>;
+    type SerializeTuple = TupleSerializer;
+    type SerializeTupleStruct = TupleSerializer;
+    type SerializeTupleVariant = Impossible<String, SerializerError>;
+    type SerializeMap = Impossible<String, SerializerError>;
+    type SerializeStruct = Impossible<String, SerializerError>;
+    type SerializeStructVariant = Impossible<String, SerializerError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        check_str(v)?;
+        Ok(v.to_string())
+    }
+
+    /// Writes bytes as lowercase hex, which is how they're read back first.
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(v.iter().map(|x| format!("{:02x}", x)).collect())
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(String::new())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(SerializerError::ExpectedAtom("newtype variant"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(SerializerError::ExpectedAtom("sequence"))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(TupleSerializer {
+            elements: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(SerializerError::ExpectedAtom("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(SerializerError::ExpectedAtom("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(SerializerError::ExpectedAtom("struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(SerializerError::ExpectedAtom("struct variant"))
+    }
+}
+
+/// Collects the members of a tuple into `(a, b, c)`.
+///
+/// Tuples are split on `,` when they are read back, so nested tuples can't be represented.
+pub struct TupleSerializer {
+    elements: Vec<String>,
+}
+
+impl TupleSerializer {
+    fn push<T>(&mut self, value: &T) -> Result<(), SerializerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let element = value.serialize(ElementSerializer)?;
+        self.elements.push(element);
+        Ok(())
+    }
+
+    fn finish(self) -> String {
+        let mut out = String::new();
+        out.push(AtomSerializer::TUPLE_START);
+        out.push_str(&self.elements.join(AtomSerializer::TUPLE_DELIMITER));
+        out.push(AtomSerializer::TUPLE_END);
+        out
+    }
+}
+
+impl ser::SerializeTuple for TupleSerializer {
+    type Ok = String;
+    type Error = SerializerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for TupleSerializer {
+    type Ok = String;
+    type Error = SerializerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+/// An [`AtomSerializer`] that refuses anything which would break a tuple apart.
+struct ElementSerializer;
+
+impl ElementSerializer {
+    fn check(element: String) -> Result<String, SerializerError> {
+        if element.contains(',') || element.contains(AtomSerializer::TUPLE_END) {
+            Err(SerializerError::ExpectedAtom(
+                "tuple member containing `,` or `)`",
+            ))
+        } else {
+            Ok(element)
+        }
+    }
+}
+
+macro_rules! forward_to_atom {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, Self::Error> {
+                AtomSerializer.$method(v).and_then(Self::check)
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for ElementSerializer {
+    type Ok = String;
+    type Error = SerializerError;
+
+    type SerializeSeq = Impossible<String, SerializerError>;
+    type SerializeTuple = Impossible<String, SerializerError>;
+    type SerializeTupleStruct = Impossible<String, SerializerError>;
+    type SerializeTupleVariant = Impossible<String, SerializerError>;
+    type SerializeMap = Impossible<String, SerializerError>;
+    type SerializeStruct = Impossible<String, SerializerError>;
+    type SerializeStructVariant = Impossible<String, SerializerError>;
+
+    forward_to_atom! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        AtomSerializer.serialize_none()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        AtomSerializer.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        AtomSerializer.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        AtomSerializer.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(SerializerError::ExpectedAtom("newtype variant"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(SerializerError::ExpectedAtom("sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(SerializerError::ExpectedAtom("nested tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(SerializerError::ExpectedAtom("nested tuple"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(SerializerError::ExpectedAtom("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(SerializerError::ExpectedAtom("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(SerializerError::ExpectedAtom("struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(SerializerError::ExpectedAtom("struct variant"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_primitives() {
+        assert_eq!(to_string(&true).unwrap(), "true");
+        assert_eq!(to_string(&-128i8).unwrap(), "-128");
+        assert_eq!(to_string(&1.234f32).unwrap(), "1.234");
+        assert_eq!(to_string("Hello World!").unwrap(), "Hello World!");
+        assert!(to_string("Hello\nWorld!").is_err());
+        assert!(to_string("x=y").is_err());
+        assert!(to_string("  padded ").is_err());
+        assert!(to_string(&' ').is_err());
+        assert_eq!(
+            ser::Serializer::serialize_bytes(AtomSerializer, &[0xde, 0xad, 0x0b]).unwrap(),
+            "dead0b"
+        );
+    }
+
+    #[test]
+    fn write_tuple() {
+        assert_eq!(
+            to_string(&(123u8, 1.5f32, "Hello World!")).unwrap(),
+            "(123, 1.5, Hello World!)"
+        );
+        assert_eq!(to_string(&(123u8,)).unwrap(), "(123)");
+        assert!(to_string(&(1, (2, 3))).is_err());
+        assert!(to_string(&("a, b",)).is_err());
+    }
+}
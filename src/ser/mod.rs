@@ -0,0 +1,790 @@
+use std::io::Write;
+
+use serde::ser::{self, Serialize};
+
+use crate::bools::BoolStyle;
+use crate::error::SerializerError;
+
+pub mod atom;
+
+use self::atom::{AtomSerializer, TupleSerializer};
+
+/// Writes `value` in its `CanonicalPath` form.
+pub fn to_string<T>(value: &T) -> Result<String, SerializerError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut buf = Vec::new();
+    to_writer(&mut buf, value)?;
+    Ok(String::from_utf8(buf).expect("the serializer only writes valid UTF-8"))
+}
+
+/// Writes `value` in its `CanonicalPath` form to `writer`.
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), SerializerError>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    let mut ser = Serializer::new(writer);
+    value.serialize(&mut ser)?;
+    ser.finish()
+}
+
+/// The order in which a [`Serializer`] writes its entries.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum KeyOrder {
+    /// Keys are sorted byte-wise, the same way SEGA's own files are.
+    ///
+    /// This puts `10` before `2` and `length` after every index.
+    #[default]
+    Sorted,
+    /// Keys are written as soon as they are serialized, i.e. in field declaration order.
+    Declaration,
+}
+
+pub struct Serializer<W> {
+    writer: W,
+    /// The key path of the node currently being written, without a trailing delimiter
+    path: String,
+    order: KeyOrder,
+    bools: BoolStyle,
+    /// The entries held back until [`Serializer::finish`] when sorting
+    entries: Vec<(String, String)>,
+}
+
+impl<W: Write> Serializer<W> {
+    const PATH_DELIMITER: char = '.';
+    const KEY_VALUE_DELIMITER: char = '=';
+    const SEQ_ENDER: &str = "length";
+
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            path: String::new(),
+            order: KeyOrder::default(),
+            bools: BoolStyle::default(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn with_order(mut self, order: KeyOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn order(&self) -> KeyOrder {
+        self.order
+    }
+
+    /// Decides how booleans are spelled, like `1` and `0` instead of `true` and `false`.
+    ///
+    /// Booleans inside of a tuple are always written as `true` and `false`.
+    pub fn with_bools(mut self, bools: BoolStyle) -> Self {
+        self.bools = bools;
+        self
+    }
+
+    pub fn bools(&self) -> BoolStyle {
+        self.bools
+    }
+
+    /// Writes out every buffered entry.
+    ///
+    /// This has to be called once the value is serialized, otherwise nothing is written when
+    /// using [`KeyOrder::Sorted`].
+    pub fn finish(&mut self) -> Result<(), SerializerError> {
+        let mut entries = std::mem::take(&mut self.entries);
+        // stable, so a key written twice keeps its relative order
+        entries.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+        for (key, value) in entries {
+            self.write_entry(&key, &value)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Descends into `key`, returning the length of the path to restore with [`Self::pop`].
+    fn push(&mut self, key: &str) -> Result<usize, SerializerError> {
+        // Lines are trimmed when they're read, and a line starting with `#` is a comment
+        if key.is_empty()
+            || key.contains(Self::PATH_DELIMITER)
+            || key.contains(Self::KEY_VALUE_DELIMITER)
+            || key.contains('\n')
+            || key.trim() != key
+            || (self.path.is_empty() && key.starts_with('#'))
+        {
+            return Err(SerializerError::InvalidKey(key.to_string()));
+        }
+        let len = self.path.len();
+        if !self.path.is_empty() {
+            self.path.push(Self::PATH_DELIMITER);
+        }
+        self.path.push_str(key);
+        Ok(len)
+    }
+
+    fn pop(&mut self, len: usize) {
+        self.path.truncate(len);
+    }
+
+    fn emit(&mut self, value: &str) -> Result<(), SerializerError> {
+        // A line with an empty key can't be read back
+        if self.path.is_empty() {
+            return Err(SerializerError::UnsupportedTopLevel);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::trace!(key = self.path.as_str(), value = value);
+        match self.order {
+            KeyOrder::Sorted => {
+                self.entries.push((self.path.clone(), value.to_string()));
+                Ok(())
+            }
+            KeyOrder::Declaration => {
+                let path = std::mem::take(&mut self.path);
+                let res = self.write_entry(&path, value);
+                self.path = path;
+                res
+            }
+        }
+    }
+
+    fn write_entry(&mut self, key: &str, value: &str) -> Result<(), SerializerError> {
+        writeln!(self.writer, "{}{}{}", key, Self::KEY_VALUE_DELIMITER, value)?;
+        Ok(())
+    }
+
+    fn emit_atom<T>(&mut self, value: &T) -> Result<(), SerializerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let atom = value.serialize(AtomSerializer)?;
+        self.emit(&atom)
+    }
+}
+
+impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = SerializerError;
+
+    type SerializeSeq = SeqSerializer<'a, W>;
+    type SerializeTuple = Compound<'a, W, TupleSerializer>;
+    type SerializeTupleStruct = Compound<'a, W, TupleSerializer>;
+    type SerializeTupleVariant = Compound<'a, W, TupleSerializer>;
+    type SerializeMap = Compound<'a, W, Option<String>>;
+    type SerializeStruct = Compound<'a, W, ()>;
+    type SerializeStructVariant = Compound<'a, W, ()>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.emit(self.bools.spell(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(&v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.emit_atom(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        let atom = ser::Serializer::serialize_bytes(AtomSerializer, v)?;
+        self.emit(&atom)
+    }
+
+    /// Missing keys are read back as `None`, so nothing is written.
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    /// The parser reads units without consuming a line, so nothing is written.
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.emit(variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let len = self.push(variant)?;
+        value.serialize(&mut *self)?;
+        self.pop(len);
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SeqSerializer {
+            ser: self,
+            index: 0,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        let state = AtomSerializer.serialize_tuple(len)?;
+        Ok(Compound {
+            ser: self,
+            restore: None,
+            state,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        let restore = Some(self.push(variant)?);
+        let state = AtomSerializer.serialize_tuple(len)?;
+        Ok(Compound {
+            ser: self,
+            restore,
+            state,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(Compound {
+            ser: self,
+            restore: None,
+            state: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(Compound {
+            ser: self,
+            restore: None,
+            state: (),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        let restore = Some(self.push(variant)?);
+        Ok(Compound {
+            ser: self,
+            restore,
+            state: (),
+        })
+    }
+}
+
+/// Writes every element under its index, followed by a `length` entry.
+pub struct SeqSerializer<'a, W> {
+    ser: &'a mut Serializer<W>,
+    /// The index of the next element
+    index: usize,
+}
+
+impl<'a, W: Write> ser::SerializeSeq for SeqSerializer<'a, W> {
+    type Ok = ();
+    type Error = SerializerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let len = self.ser.push(&self.index.to_string())?;
+        value.serialize(&mut *self.ser)?;
+        self.ser.pop(len);
+        self.index += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        let len = self.ser.push(Serializer::<W>::SEQ_ENDER)?;
+        self.ser.emit(&self.index.to_string())?;
+        self.ser.pop(len);
+        Ok(())
+    }
+}
+
+/// Any compound value that is written under the current path.
+pub struct Compound<'a, W, S> {
+    ser: &'a mut Serializer<W>,
+    /// The path to go back to once the compound is finished, if it pushed a variant name
+    restore: Option<usize>,
+    state: S,
+}
+
+impl<'a, W: Write, S> Compound<'a, W, S> {
+    fn field<T>(&mut self, key: &str, value: &T) -> Result<(), SerializerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let len = self.ser.push(key)?;
+        value.serialize(&mut *self.ser)?;
+        self.ser.pop(len);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), SerializerError> {
+        if let Some(len) = self.restore {
+            self.ser.pop(len);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, W: Write> Compound<'a, W, TupleSerializer> {
+    fn finish_tuple(self) -> Result<(), SerializerError> {
+        let atom = ser::SerializeTuple::end(self.state)?;
+        self.ser.emit(&atom)?;
+        if let Some(len) = self.restore {
+            self.ser.pop(len);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, W: Write> ser::SerializeTuple for Compound<'a, W, TupleSerializer> {
+    type Ok = ();
+    type Error = SerializerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeTuple::serialize_element(&mut self.state, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish_tuple()
+    }
+}
+
+impl<'a, W: Write> ser::SerializeTupleStruct for Compound<'a, W, TupleSerializer> {
+    type Ok = ();
+    type Error = SerializerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeTuple::serialize_element(&mut self.state, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish_tuple()
+    }
+}
+
+impl<'a, W: Write> ser::SerializeTupleVariant for Compound<'a, W, TupleSerializer> {
+    type Ok = ();
+    type Error = SerializerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeTuple::serialize_element(&mut self.state, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish_tuple()
+    }
+}
+
+impl<'a, W: Write> ser::SerializeMap for Compound<'a, W, Option<String>> {
+    type Ok = ();
+    type Error = SerializerError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        // Keys are checked once they're pushed, with an error that says it's the key
+        let key = key.serialize(AtomSerializer).or_else(|err| match err {
+            SerializerError::InvalidValue(key) => Ok(key),
+            err => Err(err),
+        })?;
+        self.state = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self
+            .state
+            .take()
+            .ok_or_else(|| SerializerError::Custom("value serialized before its key".into()))?;
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'a, W: Write> ser::SerializeStruct for Compound<'a, W, ()> {
+    type Ok = ();
+    type Error = SerializerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'a, W: Write> ser::SerializeStructVariant for Compound<'a, W, ()> {
+    type Ok = ();
+    type Error = SerializerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::from_str;
+
+    fn to_string_unsorted<T: Serialize>(value: &T) -> Result<String, SerializerError> {
+        let mut ser = Serializer::new(Vec::new()).with_order(KeyOrder::Declaration);
+        value.serialize(&mut ser)?;
+        ser.finish()?;
+        Ok(String::from_utf8(ser.into_inner()).unwrap())
+    }
+
+    #[test]
+    fn write_map() {
+        let mut map = BTreeMap::new();
+        map.insert("bar", 2);
+        map.insert("foo", 1);
+        assert_eq!(to_string(&map).unwrap(), "bar=2\nfoo=1\n");
+    }
+
+    #[test]
+    fn write_seq() {
+        let data = (0..3).collect::<Vec<i64>>();
+        let out = to_string(&data).unwrap();
+        assert_eq!(out, "0=0\n1=1\n2=2\nlength=3\n");
+        assert_eq!(from_str::<Vec<i64>>(&out), Ok(data));
+    }
+
+    #[test]
+    fn write_seq_sorted() {
+        let data = (0..12).collect::<Vec<i64>>();
+        let expected = "0=0
+1=1
+10=10
+11=11
+2=2
+3=3
+4=4
+5=5
+6=6
+7=7
+8=8
+9=9
+length=12
+";
+        let out = to_string(&data).unwrap();
+        assert_eq!(out, expected);
+        assert_eq!(from_str::<Vec<i64>>(&out), Ok(data.clone()));
+
+        let out = to_string_unsorted(&data).unwrap();
+        assert!(out.starts_with("0=0\n1=1\n2=2\n"));
+        assert!(out.ends_with("11=11\nlength=12\n"));
+    }
+
+    #[test]
+    fn write_struct() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Test {
+            foo: u32,
+            bar: f32,
+            baz: bool,
+            inner: Inner,
+            tuple: (u8, f32),
+            list: Vec<Inner>,
+            missing: Option<u32>,
+        }
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Inner {
+            name: String,
+            id: u32,
+        }
+        let data = Test {
+            foo: 32,
+            bar: 1.234,
+            baz: true,
+            inner: Inner {
+                name: "John Smith".to_string(),
+                id: 69,
+            },
+            tuple: (1, 0.5),
+            list: vec![Inner {
+                name: "Jane Doe".to_string(),
+                id: 420,
+            }],
+            missing: None,
+        };
+        let expected = "foo=32
+bar=1.234
+baz=true
+inner.name=John Smith
+inner.id=69
+tuple=(1, 0.5)
+list.0.name=Jane Doe
+list.0.id=420
+list.length=1
+";
+        let out = to_string_unsorted(&data).unwrap();
+        assert_eq!(out, expected);
+        assert_eq!(from_str::<Test>(&out).as_ref(), Ok(&data));
+
+        let expected = "bar=1.234
+baz=true
+foo=32
+inner.id=69
+inner.name=John Smith
+list.0.id=420
+list.0.name=Jane Doe
+list.length=1
+tuple=(1, 0.5)
+";
+        let out = to_string(&data).unwrap();
+        assert_eq!(out, expected);
+        assert_eq!(from_str::<Test>(&out), Ok(data));
+    }
+
+    #[test]
+    fn write_enum() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Test {
+            None,
+            Foo(u32),
+            Bar(u32, f32),
+            Baz(String),
+            Quux { foo: u32, bar: f32 },
+            Foobar(Foobar),
+        }
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Foobar {
+            foo: u32,
+            bar: f32,
+        }
+        let data = vec![
+            Test::None,
+            Test::Foo(123),
+            Test::Bar(123, 1.5),
+            Test::Baz("Hello World!".to_string()),
+            Test::Quux { foo: 123, bar: 1.5 },
+            Test::Foobar(Foobar { foo: 123, bar: 1.5 }),
+        ];
+        let expected = "0=None
+1.Foo=123
+2.Bar=(123, 1.5)
+3.Baz=Hello World!
+4.Quux.foo=123
+4.Quux.bar=1.5
+5.Foobar.foo=123
+5.Foobar.bar=1.5
+length=6
+";
+        let out = to_string_unsorted(&data).unwrap();
+        assert_eq!(out, expected);
+        assert_eq!(from_str::<Vec<Test>>(&out), Ok(data));
+    }
+
+    #[test]
+    fn write_bools() {
+        let mut map = BTreeMap::new();
+        map.insert("fov_is_horizontal", true);
+        map.insert("loop", false);
+        assert_eq!(
+            to_string(&map).unwrap(),
+            "fov_is_horizontal=true\nloop=false\n"
+        );
+        let mut ser = Serializer::new(Vec::new()).with_bools(BoolStyle::OneZero);
+        map.serialize(&mut ser).unwrap();
+        ser.finish().unwrap();
+        let out = String::from_utf8(ser.into_inner()).unwrap();
+        assert_eq!(out, "fov_is_horizontal=1\nloop=0\n");
+        assert_eq!(from_str::<BTreeMap<&str, bool>>(&out).unwrap(), map);
+    }
+
+    #[test]
+    fn write_invalid_key() {
+        let mut map = BTreeMap::new();
+        map.insert("foo=bar", 1);
+        match to_string(&map) {
+            Err(SerializerError::InvalidKey(key)) => assert_eq!(key, "foo=bar"),
+            e => unreachable!("{:?}", e),
+        }
+        let mut map = BTreeMap::new();
+        map.insert("a.b", 1);
+        match to_string(&map) {
+            Err(SerializerError::InvalidKey(key)) => assert_eq!(key, "a.b"),
+            e => unreachable!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn write_round_trip() {
+        fn round_trip(key: &str, value: &str) -> Result<(), SerializerError> {
+            let mut map = BTreeMap::new();
+            map.insert(key, value);
+            let out = to_string(&map)?;
+            assert_eq!(from_str::<BTreeMap<&str, &str>>(&out).unwrap(), map);
+            Ok(())
+        }
+        round_trip("a", "x y").unwrap();
+        round_trip("a", "#x").unwrap();
+        assert!(matches!(
+            round_trip("a", "x=y"),
+            Err(SerializerError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            round_trip("a", "  padded "),
+            Err(SerializerError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            round_trip("#a", "x"),
+            Err(SerializerError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            round_trip(" a", "x"),
+            Err(SerializerError::InvalidKey(_))
+        ));
+
+        let mut map = BTreeMap::new();
+        map.insert("b", BTreeMap::from([("#a", "x")]));
+        let out = to_string(&map).unwrap();
+        assert_eq!(out, "b.#a=x\n");
+        assert_eq!(from_str::<BTreeMap<_, _>>(&out).unwrap(), map);
+    }
+
+    #[test]
+    fn write_top_level_value() {
+        assert!(matches!(
+            to_string(&5),
+            Err(SerializerError::UnsupportedTopLevel)
+        ));
+        assert!(matches!(
+            to_string(&(1, 2)),
+            Err(SerializerError::UnsupportedTopLevel)
+        ));
+        assert_eq!(to_string(&Some(vec![5])).unwrap(), "0=5\nlength=1\n");
+    }
+}
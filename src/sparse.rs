@@ -0,0 +1,291 @@
+//! Sequences whose indices have gaps in them.
+//!
+//! Reading a file into a `Vec<T>` skips over missing indices, so `0`, `1` and `5` become a
+//! three element `Vec`. [`Sparse`] keeps every element at its index instead, and can be used
+//! for `Vec<Option<T>>` fields with `#[serde(with = "serde_divatree::sparse")]`.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Table {
+//!     #[serde(with = "serde_divatree::sparse")]
+//!     chara: Vec<Option<String>>,
+//! }
+//!
+//! let input = "chara.0=MIK
+//! chara.1=RIN
+//! chara.5=LEN
+//! chara.length=6
+//! ";
+//! let table: Table = serde_divatree::from_str(input).unwrap();
+//! assert_eq!(table.chara[1].as_deref(), Some("RIN"));
+//! assert_eq!(table.chara[2], None);
+//! assert_eq!(serde_divatree::to_string(&table).unwrap(), input);
+//! ```
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Unexpected, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use crate::error::IndexOverflow;
+use crate::serde::SEQ_ENDER;
+
+/// The most elements a sequence that's read can be missing, so that one huge index or
+/// `length` can't make [`Sparse::into_vec`] run out of memory.
+pub const MAX_GAPS: usize = 1 << 16;
+
+/// A sequence that keeps the index of every element, along with its declared `length`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub struct Sparse<T> {
+    elements: BTreeMap<usize, T>,
+    length: usize,
+}
+
+impl<T> Default for Sparse<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Sparse<T> {
+    pub fn new() -> Self {
+        Self {
+            elements: BTreeMap::new(),
+            length: 0,
+        }
+    }
+
+    /// The `length` of the sequence, which is past its last element.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Changes the `length`, dropping every element at or past it.
+    pub fn set_len(&mut self, length: usize) {
+        self.elements.split_off(&length);
+        self.length = length;
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.elements.get(&index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.elements.get_mut(&index)
+    }
+
+    /// Sets the element at `index`, growing the `length` to fit it.
+    ///
+    /// Fails if `index` is `usize::MAX`, as the `length` can't fit it.
+    pub fn insert(&mut self, index: usize, value: T) -> Result<Option<T>, IndexOverflow> {
+        let end = index.checked_add(1).ok_or(IndexOverflow(index))?;
+        self.length = self.length.max(end);
+        Ok(self.elements.insert(index, value))
+    }
+
+    /// Leaves a gap at `index`, keeping the `length` as it is.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        self.elements.remove(&index)
+    }
+
+    /// Every element along with its index.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.elements.iter().map(|(index, value)| (*index, value))
+    }
+
+    /// How many elements are missing below the `length`.
+    pub fn gaps(&self) -> usize {
+        self.length - self.elements.len()
+    }
+
+    /// Turns the sequence into a `Vec` of `length` elements, with `None` in the gaps.
+    pub fn into_vec(self) -> Vec<Option<T>> {
+        let mut vec: Vec<_> = std::iter::repeat_with(|| None).take(self.length).collect();
+        for (index, value) in self.elements {
+            vec[index] = Some(value);
+        }
+        vec
+    }
+}
+
+impl<T> From<Vec<Option<T>>> for Sparse<T> {
+    fn from(vec: Vec<Option<T>>) -> Self {
+        let length = vec.len();
+        let elements = vec
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, value)| Some((index, value?)))
+            .collect();
+        Self { elements, length }
+    }
+}
+
+impl<T> FromIterator<(usize, T)> for Sparse<T> {
+    /// # Panics
+    ///
+    /// Panics if an index is `usize::MAX`, like [`Sparse::insert`] fails.
+    fn from_iter<I: IntoIterator<Item = (usize, T)>>(iter: I) -> Self {
+        let mut sparse = Self::new();
+        for (index, value) in iter {
+            sparse
+                .insert(index, value)
+                .expect("index is too large for a length");
+        }
+        sparse
+    }
+}
+
+impl<T: Serialize> Serialize for Sparse<T> {
+    /// Writes a sequence of `length` elements, where the gaps are `None`.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.length))?;
+        for index in 0..self.length {
+            seq.serialize_element(&self.elements.get(&index))?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Sparse<T> {
+    /// Reads a sequence node as a map from its indices, so none of them get skipped.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(SparseVisitor(PhantomData))
+    }
+}
+
+struct SparseVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for SparseVisitor<T> {
+    type Value = Sparse<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut sparse = Sparse::new();
+        let mut length = 0;
+        while let Some(key) = map.next_key::<String>()? {
+            if SEQ_ENDER.iter().any(|x| key.eq_ignore_ascii_case(x)) {
+                length = map.next_value()?;
+                continue;
+            }
+            let index = key
+                .parse::<usize>()
+                .map_err(|_| de::Error::invalid_value(Unexpected::Str(&key), &"an index"))?;
+            sparse
+                .insert(index, map.next_value()?)
+                .map_err(de::Error::custom)?;
+        }
+        sparse.length = sparse.length.max(length);
+        if sparse.gaps() > MAX_GAPS {
+            let expected = format!("at most {} missing elements", MAX_GAPS);
+            return Err(de::Error::invalid_length(sparse.length, &expected.as_str()));
+        }
+        Ok(sparse)
+    }
+}
+
+/// Reads a sparse sequence into a `Vec` with `None` in the gaps.
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Sparse::deserialize(deserializer).map(Sparse::into_vec)
+}
+
+/// Writes `vec` back with every element at its index, skipping the gaps.
+pub fn serialize<S, T>(vec: &[Option<T>], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    vec.serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{from_str, to_string};
+
+    #[test]
+    fn read_sparse() {
+        #[derive(Debug, PartialEq, Deserialize, Serialize)]
+        struct Test {
+            foo: Sparse<u32>,
+            bar: Sparse<Inner>,
+            empty: Sparse<u32>,
+        }
+        #[derive(Debug, PartialEq, Deserialize, Serialize)]
+        struct Inner {
+            name: String,
+        }
+        let input = "bar.1.name=Foo
+bar.length=2
+empty.length=0
+foo.0=1
+foo.1=2
+foo.10=10
+foo.5=5
+foo.length=12
+";
+        let data: Test = from_str(input).unwrap();
+        assert_eq!(
+            data.foo.iter().collect::<Vec<_>>(),
+            vec![(0, &1), (1, &2), (5, &5), (10, &10)]
+        );
+        assert_eq!(data.foo.len(), 12);
+        assert_eq!(data.bar.get(0), None);
+        assert_eq!(data.bar.get(1).map(|x| x.name.as_str()), Some("Foo"));
+        assert!(data.empty.is_empty());
+        assert_eq!(to_string(&data).unwrap(), input);
+    }
+
+    #[test]
+    fn sparse_vec() {
+        let mut sparse: Sparse<_> = vec![Some(1), None, Some(3), None].into();
+        assert_eq!(sparse.len(), 4);
+        sparse.insert(6, 7).unwrap();
+        assert_eq!(sparse.insert(usize::MAX, 8), Err(IndexOverflow(usize::MAX)));
+        sparse.remove(0);
+        assert_eq!(
+            sparse.clone().into_vec(),
+            vec![None, None, Some(3), None, None, None, Some(7)]
+        );
+        sparse.set_len(3);
+        assert_eq!(sparse.into_vec(), vec![None, None, Some(3)]);
+        assert!(from_str::<Sparse<u32>>("foo=1").is_err());
+    }
+
+    #[test]
+    fn read_sparse_huge() {
+        assert!(from_str::<Sparse<u32>>("18446744073709551615=1").is_err());
+        assert!(from_str::<Sparse<u32>>("18446744073709551616=1").is_err());
+        assert!(from_str::<Sparse<u32>>("0=1\nlength=99999999999").is_err());
+        assert!(from_str::<Sparse<u32>>("99999999999=1").is_err());
+        let data = from_str::<Sparse<u32>>("0=1\nlength=65537").unwrap();
+        assert_eq!(data.gaps(), MAX_GAPS);
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::ops::{Index, IndexMut, Range};
+use std::str::FromStr;
+
+use crate::de::{KeyValue, Source};
+use crate::error::{DeserializerError, ErrorKind};
+use crate::serde::SEQ_ENDER;
+use crate::Options;
+
+mod de;
+mod seq;
+mod ser;
+
+pub(crate) use self::de::{RawValueAccess, TOKEN};
+pub(crate) use self::seq::index;
+pub use self::seq::Seq;
+
+/// Any node of a `CanonicalPath` file.
+///
+/// Unlike deserializing into a map, this keeps sequences apart from maps and never mistakes
+/// a sequence's `length` for an element. Sequences keep their indices and `length` as they
+/// were written, so a file is written back the way it was read.
+///
+/// # Example
+///
+/// ```
+/// use serde_divatree::Value;
+///
+/// let input = "pv_001.difficulty.hard.0.level=PV_LV_07_5
+/// pv_001.difficulty.hard.length=1
+/// pv_001.song_name=Foo
+/// ";
+/// let pv_db: Value = input.parse().unwrap();
+/// assert_eq!(pv_db["pv_001.difficulty.hard.0.level"].as_str(), Some("PV_LV_07_5"));
+/// assert_eq!(pv_db.to_string(), input);
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub enum Value {
+    /// A single value, kept as it was written.
+    Atom(String),
+    /// A `(a, b, c)` value.
+    Tuple(Vec<String>),
+    /// Indexed elements, usually followed by a `length`.
+    Seq(Seq),
+    /// Named children.
+    Map(BTreeMap<String, Value>),
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Self::Map(BTreeMap::new())
+    }
+}
+
+impl Value {
+    const TUPLE_START: char = '(';
+    const TUPLE_END: char = ')';
+    const TUPLE_DELIMITER: char = ',';
+
+    /// Parses the right hand side of a key value pair.
+    pub fn atom(value: &str) -> Self {
+        match value
+            .strip_prefix(Self::TUPLE_START)
+            .and_then(|x| x.strip_suffix(Self::TUPLE_END))
+        {
+            Some(members) => {
+                let mut members: Vec<_> = members
+                    .split(Self::TUPLE_DELIMITER)
+                    .map(|x| x.trim().to_string())
+                    .collect();
+                // a trailing delimiter, e.g. `(123, )`
+                if members.last().is_some_and(String::is_empty) {
+                    members.pop();
+                }
+                Self::Tuple(members)
+            }
+            None => Self::Atom(value.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Atom(atom) => Some(atom),
+            _ => None,
+        }
+    }
+
+    pub fn as_tuple(&self) -> Option<&[String]> {
+        match self {
+            Self::Tuple(tuple) => Some(tuple),
+            _ => None,
+        }
+    }
+
+    /// The right hand side of the line this value is written as, unless it's a node.
+    pub fn to_atom(&self) -> Option<String> {
+        match self {
+            Self::Atom(atom) => Some(atom.clone()),
+            Self::Tuple(members) => Some(format!("({})", members.join(", "))),
+            _ => None,
+        }
+    }
+
+    pub fn as_seq(&self) -> Option<&Seq> {
+        match self {
+            Self::Seq(seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    pub fn as_seq_mut(&mut self) -> Option<&mut Seq> {
+        match self {
+            Self::Seq(seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Self::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut BTreeMap<String, Value>> {
+        match self {
+            Self::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Looks up a direct child by its key, or its index for sequences.
+    pub fn child(&self, key: &str) -> Option<&Value> {
+        match self {
+            Self::Map(map) => map.get(key),
+            Self::Seq(seq) => seq.get(index(key)?),
+            _ => None,
+        }
+    }
+
+    pub fn child_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match self {
+            Self::Map(map) => map.get_mut(key),
+            Self::Seq(seq) => seq.get_mut(index(key)?),
+            _ => None,
+        }
+    }
+
+    /// Looks up a node by its dotted key path. e.g. `pv_001.difficulty.hard.0`
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        Self::segments(path).try_fold(self, |node, key| node.child(key))
+    }
+
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut Value> {
+        Self::segments(path).try_fold(self, |node, key| node.child_mut(key))
+    }
+
+    fn segments(path: &str) -> impl Iterator<Item = &str> {
+        path.split(KeyValue::PATH_DELIMITER)
+            .filter(|x| !x.is_empty())
+    }
+
+    /// Builds a tree out of `key=value` lines.
+    ///
+    /// The lines don't need to be sorted.
+    pub(crate) fn from_lines<'a>(
+        lines: impl IntoIterator<Item = &'a str>,
+        source: Source<'a>,
+    ) -> Result<Self, DeserializerError> {
+        let mut root = Self::default();
+        for line in lines {
+            let kv = KeyValue::new(line).ok_or_else(|| {
+                DeserializerError::from(ErrorKind::ExpectedKeyValuePair {
+                    span: source.span(line),
+                })
+                .locate(source)
+            })?;
+            root.insert(kv.path(), kv.value)
+                .map_err(|err| DeserializerError::from(err(source.span(kv.key))).locate(source))?;
+        }
+        Ok(root.into_seqs())
+    }
+
+    /// Sets the node at `path`, or returns the error for the span of its key.
+    fn insert<'a>(
+        &mut self,
+        mut path: impl Iterator<Item = &'a str>,
+        value: &str,
+    ) -> Result<(), fn(Range<usize>) -> ErrorKind> {
+        match (path.next(), self) {
+            (None, Self::Map(map)) if !map.is_empty() => {
+                Err(|span| ErrorKind::ExpectedValueNode { span })
+            }
+            (None, node) => {
+                *node = Self::atom(value);
+                Ok(())
+            }
+            (Some(key), Self::Map(map)) => {
+                map.entry(key.to_string()).or_default().insert(path, value)
+            }
+            (Some(_), _) => Err(|span| ErrorKind::ExpectedKeyNode { span }),
+        }
+    }
+
+    /// Turns every map indexed by integers into a sequence.
+    ///
+    /// A map is only a sequence when it can be written back the same way, so it needs at most
+    /// one `length` whose value is an index as well.
+    fn into_seqs(self) -> Self {
+        let map = match self {
+            Self::Map(map) => map,
+            _ => return self,
+        };
+        let is_ender = |key: &str| SEQ_ENDER.iter().any(|x| key.eq_ignore_ascii_case(x));
+        let enders: Vec<_> = map.keys().filter(|x| is_ender(x)).collect();
+        let length = match enders.as_slice() {
+            [] => Some(None),
+            [ender] => map[*ender].as_str().and_then(index).map(Some),
+            _ => None,
+        };
+        let is_seq = length.is_some()
+            && map.keys().any(|x| index(x).is_some())
+            && map.keys().all(|x| index(x).is_some() || is_ender(x));
+        if !is_seq {
+            return Self::Map(
+                map.into_iter()
+                    .map(|(key, value)| (key, value.into_seqs()))
+                    .collect(),
+            );
+        }
+        let ender = enders
+            .first()
+            .map_or(SEQ_ENDER[0], |x| x.as_str())
+            .to_string();
+        let mut seq = Seq::new().with_ender(&ender).with_length(length.flatten());
+        for (key, value) in map {
+            if let Some(index) = index(&key) {
+                seq.elements.insert(index, value.into_seqs());
+            }
+        }
+        Self::Seq(seq)
+    }
+}
+
+impl FromStr for Value {
+    type Err = DeserializerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source = Source::Str(s);
+        Self::from_lines(Options::default().lines(s.lines(), source)?, source)
+    }
+}
+
+impl Display for Value {
+    /// Writes a node as its lines, and an atom or tuple as the right hand side of one.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(atom) = self.to_atom() {
+            return f.write_str(&atom);
+        }
+        let out = crate::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&out)
+    }
+}
+
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, path: &str) -> &Self::Output {
+        self.get(path)
+            .unwrap_or_else(|| panic!("no value at `{}`", path))
+    }
+}
+
+impl IndexMut<&str> for Value {
+    fn index_mut(&mut self, path: &str) -> &mut Self::Output {
+        self.get_mut(path)
+            .unwrap_or_else(|| panic!("no value at `{}`", path))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::atom(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::atom(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_derive::Deserialize;
+
+    use super::*;
+    use crate::{from_str, to_string};
+
+    #[test]
+    fn read_value() {
+        let input = "foo.0=1
+foo.1=(1, 2.5, bar)
+foo.10=10
+foo.2=2
+foo.length=3
+bar.baz=007
+bar.quux=1.00
+";
+        let value: Value = input.parse().unwrap();
+        let foo = value["foo"].as_seq().unwrap();
+        assert_eq!(foo.len(), 4);
+        assert_eq!(foo.length(), Some(3));
+        assert_eq!(foo.get(0), Some(&Value::Atom("1".into())));
+        assert_eq!(
+            foo.get(1),
+            Some(&Value::Tuple(vec!["1".into(), "2.5".into(), "bar".into()]))
+        );
+        assert_eq!(foo.get(2), Some(&Value::Atom("2".into())));
+        assert_eq!(foo.get(3), None);
+        assert_eq!(value["foo.10"], Value::Atom("10".into()));
+        assert_eq!(value.get("bar.baz").and_then(Value::as_str), Some("007"));
+        assert_eq!(value.get("bar.quux").and_then(Value::as_str), Some("1.00"));
+        assert_eq!(value.get("foo.3.bar"), None);
+        assert_eq!(value.get("foo.length"), None);
+    }
+
+    #[test]
+    fn read_value_unsorted() {
+        let input = "foo.bar=1
+baz=2
+foo.quux=3
+";
+        let value: Value = Value::from_lines(input.lines(), Source::Unknown).unwrap();
+        assert_eq!(value["foo.quux"], Value::Atom("3".into()));
+        assert_eq!(value["foo"].as_map().map(BTreeMap::len), Some(2));
+    }
+
+    #[test]
+    fn read_value_conflict() {
+        assert_eq!(
+            "foo=1\nfoo.bar=2"
+                .parse::<Value>()
+                .map_err(DeserializerError::into_kind),
+            Err(ErrorKind::ExpectedKeyNode { span: 6..13 })
+        );
+        let input = "foo.bar=2\nfoo=1";
+        let err = Value::from_lines(input.lines(), Source::Str(input)).unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::ExpectedValueNode { span: 10..13 });
+        assert_eq!(err.to_string(), format!("2:1: {}", err.kind()));
+    }
+
+    #[test]
+    fn read_nested_value() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Test {
+            foo: u32,
+            other: Value,
+            tuple: Value,
+            last: String,
+        }
+        let input = "foo=1
+other.0=007
+other.1=1.00
+other.length=2
+tuple=(1, 2)
+last=bar
+";
+        let data: Test = from_str(input).unwrap();
+        assert_eq!(data.foo, 1);
+        assert_eq!(
+            data.other,
+            Value::Seq(vec![Value::Atom("007".into()), Value::Atom("1.00".into())].into())
+        );
+        assert_eq!(data.tuple, Value::Tuple(vec!["1".into(), "2".into()]));
+        assert_eq!(data.last, "bar");
+    }
+
+    #[test]
+    fn write_value() {
+        // Gaps, a `length` that disagrees, `num` and a missing `length` are all kept, along
+        // with the padded `01`, which is a name rather than an index
+        let input = "bar.baz=007
+bar.quux=1.00
+baz.0=a
+baz.num=1
+foo.0=1
+foo.1=(1, 2.5, bar)
+foo.10=10
+foo.2=2
+foo.length=3
+padded.0=a
+padded.01=b
+quux.0=x
+quux.1=y
+";
+        let value: Value = input.parse().unwrap();
+        assert!(value["padded"].as_map().is_some());
+        assert_eq!(value["quux"].as_seq().and_then(Seq::length), None);
+        let out = to_string(&value).unwrap();
+        assert_eq!(out, input);
+        assert_eq!(out.parse::<Value>(), Ok(value));
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use super::Value;
+use crate::serde::SEQ_ENDER;
+
+/// A sequence node, kept with the indices and `length` it was written with.
+///
+/// Gaps between indices stay where they are, so a file with `0`, `1` and `10` is written
+/// back with those same indices, and a `length` that disagrees with them is kept as well.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+pub struct Seq {
+    pub(super) elements: BTreeMap<usize, Value>,
+    /// The declared `length`, if the sequence has one
+    length: Option<usize>,
+    /// The key its `length` is written under, like `length` or `num`
+    ender: String,
+}
+
+impl Default for Seq {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Seq {
+    /// An empty sequence with a `length` of `0`.
+    pub fn new() -> Self {
+        Self {
+            elements: BTreeMap::new(),
+            length: Some(0),
+            ender: SEQ_ENDER[0].to_string(),
+        }
+    }
+
+    /// Uses `ender` as the key of the `length` entry, like `num`.
+    pub fn with_ender(mut self, ender: &str) -> Self {
+        self.ender = ender.to_string();
+        self
+    }
+
+    pub fn ender(&self) -> &str {
+        &self.ender
+    }
+
+    /// Sets the declared `length`, or leaves it out with `None`.
+    pub fn with_length(mut self, length: Option<usize>) -> Self {
+        self.length = length;
+        self
+    }
+
+    /// The declared `length`, which can disagree with the elements that are there.
+    pub fn length(&self) -> Option<usize> {
+        self.length
+    }
+
+    /// The number of elements, not counting the gaps.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The index past both the last element and the declared `length`.
+    pub fn end(&self) -> usize {
+        let last = self
+            .elements
+            .keys()
+            .next_back()
+            .map_or(0, |x| x.saturating_add(1));
+        last.max(self.length.unwrap_or_default())
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.elements.get(&index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Value> {
+        self.elements.get_mut(&index)
+    }
+
+    /// Sets the element at `index`, growing the declared `length` to fit it.
+    pub fn insert(&mut self, index: usize, value: Value) -> Option<Value> {
+        if let Some(length) = &mut self.length {
+            *length = (*length).max(index.saturating_add(1));
+        }
+        self.elements.insert(index, value)
+    }
+
+    /// Adds an element at [`Self::end`], growing the declared `length` to fit it.
+    pub fn push(&mut self, value: Value) {
+        let index = self.end();
+        self.insert(index, value);
+    }
+
+    /// Removes the element at `index`, moving every element after it down by one and
+    /// shrinking the declared `length` to match.
+    pub fn remove(&mut self, index: usize) -> Option<Value> {
+        let removed = self.elements.remove(&index)?;
+        let after = self.elements.split_off(&index);
+        self.elements
+            .extend(after.into_iter().map(|(i, value)| (i - 1, value)));
+        if let Some(length) = &mut self.length {
+            if *length > index {
+                *length -= 1;
+            }
+        }
+        Some(removed)
+    }
+
+    /// Renumbers the elements from `0` and sets the `length` to how many there are.
+    pub fn resequence(&mut self) {
+        let elements = std::mem::take(&mut self.elements);
+        self.elements = elements.into_values().enumerate().collect();
+        self.length = Some(self.elements.len());
+    }
+
+    /// Every element along with its index.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Value)> {
+        self.elements.iter().map(|(index, value)| (*index, value))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut Value)> {
+        self.elements
+            .iter_mut()
+            .map(|(index, value)| (*index, value))
+    }
+
+    /// Whether the elements are indexed from `0` without gaps, followed by a matching
+    /// `length`, which is how a `Vec` is written.
+    fn is_dense(&self) -> bool {
+        self.length == Some(self.elements.len())
+            && self.ender == SEQ_ENDER[0]
+            && self
+                .elements
+                .keys()
+                .next_back()
+                .map_or(true, |x| x + 1 == self.len())
+    }
+}
+
+impl From<Vec<Value>> for Seq {
+    fn from(values: Vec<Value>) -> Self {
+        let length = values.len();
+        Self {
+            elements: values.into_iter().enumerate().collect(),
+            length: Some(length),
+            ender: SEQ_ENDER[0].to_string(),
+        }
+    }
+}
+
+impl Serialize for Seq {
+    /// Writes a plain sequence when it's dense, and a map from every index and the `length`
+    /// otherwise.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.is_dense() {
+            return serializer.collect_seq(self.elements.values());
+        }
+        let len = self.elements.len() + usize::from(self.length.is_some());
+        let mut map = serializer.serialize_map(Some(len))?;
+        for (index, value) in &self.elements {
+            map.serialize_entry(&index.to_string(), value)?;
+        }
+        if let Some(length) = self.length {
+            map.serialize_entry(&self.ender, &length.to_string())?;
+        }
+        map.end()
+    }
+}
+
+/// The index a key stands for.
+///
+/// Keys with leading zeros, like the `001` of `lyric.001`, are names instead, as they'd be
+/// written back differently.
+pub(crate) fn index(key: &str) -> Option<usize> {
+    if !key.chars().all(|x| x.is_ascii_digit()) || (key.len() > 1 && key.starts_with('0')) {
+        return None;
+    }
+    key.parse().ok().filter(|x| *x != usize::MAX)
+}
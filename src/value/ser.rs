@@ -0,0 +1,23 @@
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+
+use super::Value;
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Atom(atom) => serializer.serialize_str(atom),
+            Self::Tuple(members) => {
+                let mut tuple = serializer.serialize_tuple(members.len())?;
+                for member in members {
+                    tuple.serialize_element(member)?;
+                }
+                tuple.end()
+            }
+            Self::Seq(seq) => seq.serialize(serializer),
+            Self::Map(map) => map.serialize(serializer),
+        }
+    }
+}